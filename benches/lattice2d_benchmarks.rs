@@ -1,6 +1,7 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use ising_lib::lattice2d::Lattice2d;
 use ising_lib::monte_carlo_measurement::{MonteCarlo, MonteCarloParams};
+use ising_lib::pool;
 
 // Criterion links:
 // https://bheisler.github.io/criterion.rs/criterion/
@@ -27,7 +28,7 @@ fn bench_sample_energy(c: &mut Criterion) {
     };
     c.bench_function("sample energy", move |b| {
         b.iter(|| {
-            let _erg_samples: Vec<Vec<f64>> = lattice.sample_energy(&params);
+            let _erg_samples = lattice.sample_energy(&params);
         })
     });
 }
@@ -42,7 +43,7 @@ fn bench_sample_energy_parallel(c: &mut Criterion) {
     };
     c.bench_function("sample energy parallel", move |b| {
         b.iter(|| {
-            let _erg_samples = lattice.sample_energy_parallel(&params);
+            let _erg_samples = lattice.sample_energy_parallel(&params, pool::default_n_threads()).unwrap();
         })
     });
 }
@@ -43,7 +43,7 @@ fn main() -> Result<(), Box<dyn Error>> {
             beta,   // 1/Tkb
         );
 
-        samples.push(lattice.sample_neighbor_correlations(&params));
+        samples.push(lattice.sample_neighbor_correlations(&params).samples);
     }
     println!("Done computing. Writing to file...");
 
@@ -1,22 +1,25 @@
 use ising_lib::lattice2d::{Lattice2d,UpdateRule,SpinType,InitType};
-use std::{thread, time};
+use ising_lib::render::{Overlay, TerminalRenderer};
+use std::{io, thread, time};
 
-fn main() {
+fn main() -> io::Result<()> {
     let seven_millis = time::Duration::from_millis(7);  // wait 7 millis to slow simulation
     // let mut lattice = Lattice2d::new_basic([45,145]);   // create a lattice
-    let mut lattice = Lattice2d::new([45,125],
+    let mut lattice = Lattice2d::new([90,125],
                                      UpdateRule::Metropolis,
                                      SpinType::SpinHalf,
                                      InitType::Random,
                                      1.0f64,
                                      0.0f64,
                                      0.20f64);
-    lattice.disp_terminal();
+    // colored half-blocks, two rows of sites per line, redrawn in place
+    let mut renderer = TerminalRenderer::new().in_place(true);
+    let mut stdout = io::stdout().lock();
+    renderer.render(&lattice, &Overlay::Spins, &mut stdout)?;
     for _ in 0..1200 {                  // 1200 frames 
-        for _ in 0..2_000 {
-            lattice.update();           // update lattice 2000 times between each frame
-        }
-        thread::sleep(seven_millis);    // wait 7 millis for smooth flip-card video display
-        lattice.disp_terminal();        // display the lattice in terminal
+        lattice.update_n(4_000);        // update lattice 4000 times between each frame
+        thread::sleep(seven_millis);    // wait 7 millis for smooth video display
+        renderer.render(&lattice, &Overlay::Spins, &mut stdout)?;
     }
+    Ok(())
 }
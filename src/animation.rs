@@ -0,0 +1,257 @@
+//! Recording the time evolution of a lattice as an animated GIF (needs
+//! the `gif` cargo feature). `GifRecorder` is a `FrameSink`, so it is
+//! driven by `Lattice2d::update_n_with_frames`, which sets the frame
+//! interval in sweeps. Each frame can be overlaid with the temperature
+//! and the magnetization.
+//!
+//! ```no_run
+//! use ising_lib::animation::{GifOptions, GifRecorder};
+//! use ising_lib::lattice2d::Lattice2d;
+//!
+//! // quench from infinite temperature to below Tc
+//! let mut lattice = Lattice2d::builder().dims([128, 128]).beta(0.6).build().unwrap();
+//! let options = GifOptions { scale: 3, ..Default::default() };
+//! let mut gif = GifRecorder::create("quench.gif", lattice.dims, options).unwrap();
+//! lattice.update_n_with_frames(128 * 128 * 500, 5, &mut gif).unwrap();
+//! gif.finish().unwrap();
+//! ```
+
+use crate::error::IsingError;
+use crate::image::{FrameSink, Palette};
+use crate::lattice2d::Lattice2d;
+use crate::measurement::Measurement;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+// indices into the global color table of the GIF
+const DOWN: u8 = 0;
+const UP: u8 = 1;
+const VACANT: u8 = 2;
+const TEXT_BACKGROUND: u8 = 3;
+const TEXT: u8 = 4;
+
+/// How the frames of the GIF look
+pub struct GifOptions {
+    pub scale: usize,       // each site is drawn as a scale x scale square
+    pub palette: Palette,   // down spins at 0.0, up spins at 1.0
+    pub delay_centis: u16,  // time between frames, in hundredths of a second
+    pub overlay_text: bool, // write T and m in the top left corner
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        GifOptions {
+            scale: 2,
+            palette: Palette::Grayscale,
+            delay_centis: 5,
+            overlay_text: true,
+        }
+    }
+}
+
+/// Encodes the frames it receives into an animated GIF
+pub struct GifRecorder<W: Write> {
+    encoder: gif::Encoder<W>,
+    options: GifOptions,
+    dims: [usize; 2], // of the lattice, every frame must match them
+    width: u16,
+    height: u16,
+}
+
+fn gif_err(err: gif::EncodingError) -> IsingError {
+    match err {
+        gif::EncodingError::Io(err) => IsingError::Io(err),
+        err => IsingError::Format(err.to_string()),
+    }
+}
+
+impl GifRecorder<BufWriter<File>> {
+    /// Records to a new file
+    pub fn create<P: AsRef<Path>>(path: P, dims: [usize; 2], options: GifOptions) -> Result<Self, IsingError> {
+        GifRecorder::new(BufWriter::new(File::create(path)?), dims, options)
+    }
+}
+
+impl<W: Write> GifRecorder<W> {
+    /// Records frames of a lattice with the given dims to `w`
+    pub fn new(w: W, dims: [usize; 2], options: GifOptions) -> Result<Self, IsingError> {
+        let scale = options.scale.max(1);
+        let size = |n: usize| {
+            n.checked_mul(scale)
+                .and_then(|n| u16::try_from(n).ok())
+                .filter(|&n| n > 0)
+                .ok_or_else(|| IsingError::InvalidDims(dims.to_vec()))
+        };
+        let (width, height) = (size(dims[1])?, size(dims[0])?);
+        let mut color_table = Vec::with_capacity(15);
+        for x in [0.0, 1.0, 0.5] {
+            color_table.extend(options.palette.color(x));
+        }
+        color_table.extend([0, 0, 0, 255, 255, 255]); // text background and text
+        let mut encoder = gif::Encoder::new(w, width, height, &color_table).map_err(gif_err)?;
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(gif_err)?;
+        Ok(GifRecorder {
+            encoder,
+            options,
+            dims,
+            width,
+            height,
+        })
+    }
+
+    /// Finishes the GIF and returns the writer
+    pub fn finish(self) -> Result<W, IsingError> {
+        Ok(self.encoder.into_inner()?)
+    }
+
+    /// Color table indices of the frame's pixels
+    fn pixels(&self, lattice: &Lattice2d) -> Vec<u8> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let scale = self.options.scale.max(1);
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push(match lattice.nodes[[y / scale, x / scale]] {
+                    1 => UP,
+                    -1 => DOWN,
+                    _ => VACANT,
+                });
+            }
+        }
+        if self.options.overlay_text {
+            let temperature = if lattice.beta > 0.0 { 1.0 / lattice.beta } else { f64::INFINITY };
+            let text = format!("T={:.3} m={:+.3}", temperature, lattice.get_spin_mean());
+            draw_text(&mut pixels, width, height, &text, 1 + scale / 2);
+        }
+        pixels
+    }
+}
+
+impl<W: Write> FrameSink for GifRecorder<W> {
+    fn frame(&mut self, lattice: &Lattice2d) -> Result<(), IsingError> {
+        if lattice.dims != self.dims {
+            return Err(IsingError::InvalidDims(lattice.dims.to_vec()));
+        }
+        let frame = gif::Frame {
+            width: self.width,
+            height: self.height,
+            delay: self.options.delay_centis,
+            buffer: Cow::Owned(self.pixels(lattice)),
+            ..Default::default()
+        };
+        self.encoder.write_frame(&frame).map_err(gif_err)
+    }
+}
+
+/// 3x5 bitmap of a character, one row per entry with the high bit on the left
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'm' => [0b000, 0b000, 0b111, 0b111, 0b101],
+        'i' => [0b010, 0b000, 0b010, 0b010, 0b010],
+        'n' => [0b000, 0b000, 0b110, 0b101, 0b101],
+        'f' => [0b011, 0b010, 0b111, 0b010, 0b010],
+        _ => [0; 5],
+    }
+}
+
+/// Draws text on a background box in the top left corner, clipped to the
+/// frame. Each font pixel is `size` x `size` pixels.
+fn draw_text(pixels: &mut [u8], width: usize, height: usize, text: &str, size: usize) {
+    let n_chars = text.chars().count();
+    // one font pixel of padding around the text and between characters
+    let box_width = (4 * n_chars + 1) * size;
+    let box_height = 7 * size;
+    for y in 0..box_height.min(height) {
+        for x in 0..box_width.min(width) {
+            pixels[y * width + x] = TEXT_BACKGROUND;
+        }
+    }
+    for (i, c) in text.chars().enumerate() {
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for col in 0..3 {
+                if bits & (0b100 >> col) == 0 {
+                    continue;
+                }
+                let (x0, y0) = ((1 + 4 * i + col) * size, (1 + row) * size);
+                for y in y0..(y0 + size).min(height) {
+                    for x in x0..(x0 + size).min(width) {
+                        pixels[y * width + x] = TEXT;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lattice2d::*;
+
+    #[test]
+    fn test_record_gif() {
+        let mut lattice = Lattice2d::builder().dims([10, 12]).build().unwrap();
+        let options = GifOptions {
+            scale: 3,
+            overlay_text: false,
+            ..Default::default()
+        };
+        let mut recorder = GifRecorder::new(Vec::new(), lattice.dims, options).unwrap();
+        lattice.update_n_with_frames(120 * 4, 2, &mut recorder).unwrap();
+        let bytes = recorder.finish().unwrap();
+        assert_eq!(&bytes[..6], b"GIF89a");
+        assert_eq!(u16::from_le_bytes([bytes[6], bytes[7]]), 36); // width
+        assert_eq!(u16::from_le_bytes([bytes[8], bytes[9]]), 30); // height
+        assert_eq!(*bytes.last().unwrap(), 0x3b); // trailer
+
+        // frames of a lattice with other dims are rejected
+        let mut recorder = GifRecorder::new(Vec::new(), [4, 4], GifOptions::default()).unwrap();
+        let result = recorder.frame(&lattice);
+        assert!(matches!(result, Err(IsingError::InvalidDims(dims)) if dims == [10, 12]));
+    }
+
+    #[test]
+    fn test_pixels_and_overlay() {
+        let lattice = Lattice2d::builder()
+            .init_array(ndarray::array![[1, -1], [-1, 1]])
+            .build()
+            .unwrap();
+        let options = GifOptions {
+            scale: 1,
+            overlay_text: false,
+            ..Default::default()
+        };
+        let recorder = GifRecorder::new(Vec::new(), lattice.dims, options).unwrap();
+        assert_eq!(recorder.pixels(&lattice), vec![UP, DOWN, DOWN, UP]);
+
+        let mut pixels = vec![DOWN; 20 * 8];
+        draw_text(&mut pixels, 20, 8, "1", 1);
+        assert_eq!(pixels[0], TEXT_BACKGROUND);
+        assert_eq!(pixels[20 + 2], TEXT); // top of the 1
+        assert_eq!(pixels[20 + 1], TEXT_BACKGROUND);
+        assert_eq!(pixels[19], DOWN); // outside the box
+    }
+
+    #[test]
+    fn test_too_large() {
+        let result = GifRecorder::new(Vec::new(), [40_000, 10], GifOptions::default());
+        assert!(matches!(result, Err(IsingError::InvalidDims(_))));
+    }
+}
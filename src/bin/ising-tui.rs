@@ -0,0 +1,272 @@
+//! Interactive terminal UI, runs a lattice live and lets you change its
+//! parameters from the keyboard. Build with the `tui` feature:
+//!
+//!     cargo run --release --features tui --bin ising-tui -- [ROWS COLS]
+//!
+//! Without ROWS and COLS the lattice is sized to fill the terminal.
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::style::Print;
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, execute, queue};
+use ising_lib::image::{Image, Palette};
+use ising_lib::lattice2d::{Boundary, Lattice2d, UpdateRule};
+use ising_lib::measurement::Measurement;
+use ising_lib::render::{sparkline, Overlay, TerminalRenderer};
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::time::Duration;
+
+const PANEL_WIDTH: usize = 36; // columns of the side panel, including a gap
+const HISTORY_LEN: usize = 512; // samples kept for the sparklines
+const FRAME_TIME: Duration = Duration::from_millis(30);
+const KEYS: [&str; 10] = [
+    "up/down  T -/+ 0.05",
+    "h/H      h -/+ 0.05",
+    "j/J      J -/+ 0.1",
+    "u        update rule",
+    "b        boundary",
+    "e        spins/energy",
+    "r        reset",
+    "space    pause, s step",
+    "w        save snapshot",
+    "q        quit",
+];
+
+/// Restores the terminal when dropped, also on panics
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(RawTerminal)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+struct App {
+    lattice: Lattice2d,
+    boundary: Boundary,
+    energy_overlay: bool,
+    paused: bool,
+    sweeps: u64,
+    energy: VecDeque<f64>, // energy per spin after each sweep
+    magnetization: VecDeque<f64>,
+    status: String,
+}
+
+impl App {
+    fn new(lattice: Lattice2d) -> Self {
+        let mut app = App {
+            lattice,
+            boundary: Boundary::Periodic,
+            energy_overlay: false,
+            paused: false,
+            sweeps: 0,
+            energy: VecDeque::with_capacity(HISTORY_LEN),
+            magnetization: VecDeque::with_capacity(HISTORY_LEN),
+            status: String::from("running"),
+        };
+        app.record();
+        app
+    }
+
+    fn temperature(&self) -> f64 {
+        1.0 / self.lattice.beta
+    }
+
+    fn set_temperature(&mut self, temperature: f64) {
+        self.lattice.beta = 1.0 / temperature.max(0.05);
+    }
+
+    /// One sweep, i.e. n_sites attempted flips
+    fn sweep(&mut self) {
+        self.lattice.update_n(self.lattice.n_sites);
+        self.sweeps += 1;
+        self.record();
+    }
+
+    fn record(&mut self) {
+        if self.energy.len() == HISTORY_LEN {
+            self.energy.pop_front();
+            self.magnetization.pop_front();
+        }
+        self.energy.push_back(self.lattice.measure_energy_per_spin());
+        self.magnetization.push_back(self.lattice.get_spin_mean());
+    }
+
+    fn reset(&mut self) {
+        self.lattice.reset_spins();
+        self.lattice.set_boundary(self.boundary);
+        self.sweeps = 0;
+        self.energy.clear();
+        self.magnetization.clear();
+        self.record();
+    }
+
+    fn save_snapshot(&mut self) {
+        let path = format!("ising-snapshot-{}.pgm", self.sweeps);
+        let image = Image::from_spins(&self.lattice.nodes, &Palette::Grayscale, 1);
+        self.status = match image.save(&path) {
+            Ok(()) => format!("saved {}", path),
+            Err(err) => format!("error: {}", err),
+        };
+    }
+
+    /// Handles a key press, returns false to quit
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Up | KeyCode::Char('+') => self.set_temperature(self.temperature() + 0.05),
+            KeyCode::Down | KeyCode::Char('-') => self.set_temperature(self.temperature() - 0.05),
+            KeyCode::Char('h') => self.lattice.h -= 0.05,
+            KeyCode::Char('H') => self.lattice.h += 0.05,
+            KeyCode::Char('j') => self.lattice.j -= 0.1,
+            KeyCode::Char('J') => self.lattice.j += 0.1,
+            KeyCode::Char('u') => {
+                let rule = match self.lattice.update_rule() {
+                    UpdateRule::Metropolis => UpdateRule::Glauber,
+                    UpdateRule::Glauber => UpdateRule::Metropolis,
+                };
+                self.lattice.set_update_rule(rule);
+            }
+            KeyCode::Char('b') => {
+                self.boundary = match self.boundary {
+                    Boundary::Periodic => Boundary::FixedUp,
+                    Boundary::FixedUp => Boundary::Interface,
+                    Boundary::Interface => Boundary::Periodic,
+                };
+                self.lattice.set_boundary(self.boundary);
+            }
+            KeyCode::Char('e') => self.energy_overlay = !self.energy_overlay,
+            KeyCode::Char('r') => self.reset(),
+            KeyCode::Char(' ') | KeyCode::Char('p') => {
+                self.paused = !self.paused;
+                self.status = String::from(if self.paused { "paused" } else { "running" });
+            }
+            KeyCode::Char('s') | KeyCode::Char('.') => {
+                self.paused = true;
+                self.status = String::from("paused");
+                self.sweep();
+            }
+            KeyCode::Char('w') => self.save_snapshot(),
+            _ => {}
+        }
+        true
+    }
+
+    /// Lines of the side panel
+    fn panel(&self) -> Vec<String> {
+        let spark_width = PANEL_WIDTH - 4;
+        let energy: Vec<f64> = self.energy.iter().copied().collect();
+        let magnetization: Vec<f64> = self.magnetization.iter().copied().collect();
+        let mut lines = vec![
+            format!("Ising {}x{}", self.lattice.dims[0], self.lattice.dims[1]),
+            String::new(),
+            format!("T     {:.3}", self.temperature()),
+            format!("h     {:+.3}", self.lattice.h),
+            format!("J     {:+.3}", self.lattice.j),
+            format!("rule  {:?}", self.lattice.update_rule()),
+            format!("bc    {:?}", self.boundary),
+            format!("sweep {}", self.sweeps),
+            String::new(),
+            format!("E/N   {:+.4}", energy.last().copied().unwrap_or(f64::NAN)),
+            sparkline(&energy, spark_width),
+            format!("m     {:+.4}", magnetization.last().copied().unwrap_or(f64::NAN)),
+            sparkline(&magnetization, spark_width),
+            String::new(),
+        ];
+        lines.extend(KEYS.iter().map(|key| key.to_string()));
+        lines.push(String::new());
+        lines.push(self.status.clone());
+        lines
+    }
+
+    fn draw<W: Write>(&self, renderer: &mut TerminalRenderer, w: &mut W) -> io::Result<()> {
+        let overlay = if self.energy_overlay { Overlay::Energy } else { Overlay::Spins };
+        let mut frame = Vec::new();
+        renderer.render(&self.lattice, &overlay, &mut frame)?;
+        let frame = String::from_utf8_lossy(&frame);
+        for (row, line) in frame.lines().enumerate() {
+            queue!(w, cursor::MoveTo(0, row as u16), Print(line))?;
+        }
+        let panel_col = self.lattice.dims[1] as u16 + 4;
+        for (row, line) in self.panel().iter().enumerate() {
+            queue!(
+                w,
+                cursor::MoveTo(panel_col, row as u16),
+                terminal::Clear(ClearType::UntilNewLine),
+                Print(line)
+            )?;
+        }
+        w.flush()
+    }
+}
+
+/// Dims from the command line, or the largest lattice that fits next to
+/// the panel (two rows of sites per line)
+fn parse_dims() -> Result<[usize; 2], String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.as_slice() {
+        [] => {
+            let (cols, rows) = terminal::size().map_err(|err| err.to_string())?;
+            let rows = 2 * (rows as usize).saturating_sub(2);
+            let cols = (cols as usize).saturating_sub(PANEL_WIDTH + 2);
+            Ok([rows.max(8), cols.max(8)])
+        }
+        [rows, cols] => {
+            let parse = |s: &String| s.parse::<usize>().map_err(|err| format!("{}: {}", s, err));
+            Ok([parse(rows)?, parse(cols)?])
+        }
+        _ => Err(String::from("usage: ising-tui [ROWS COLS]")),
+    }
+}
+
+fn main() {
+    let lattice = parse_dims().and_then(|dims| {
+        Lattice2d::builder()
+            .dims(dims)
+            .beta(1.0 / 2.5) // start above Tc, press down to quench
+            .build()
+            .map_err(|err| err.to_string())
+    });
+    let lattice = match lattice {
+        Ok(lattice) => lattice,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    if let Err(err) = run(App::new(lattice)) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run(mut app: App) -> io::Result<()> {
+    let _raw = RawTerminal::enter()?;
+    let mut stdout = io::stdout().lock();
+    let mut renderer = TerminalRenderer::new();
+    loop {
+        app.draw(&mut renderer, &mut stdout)?;
+        if event::poll(FRAME_TIME)? {
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press && !app.handle_key(key) => {
+                    return Ok(());
+                }
+                Event::Resize(_, _) => execute!(stdout, terminal::Clear(ClearType::All))?,
+                _ => {}
+            }
+        }
+        if !app.paused {
+            app.sweep();
+        }
+    }
+}
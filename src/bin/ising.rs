@@ -0,0 +1,659 @@
+//! Command-line interface to the library, so that experiments don't need
+//! a hand-written `main.rs`. Build with the `cli` feature:
+//!
+//!     cargo run --release --features cli --bin ising -- scan --temperatures 1.5:3.5:21
+//!
+//! Subcommands:
+//! - `run` evolves a single lattice and streams its observables
+//! - `scan` Monte Carlo samples a quantity over a grid of temperatures and fields
+//! - `measure` prints the observables of a saved configuration
+//! - `render` draws a configuration in the terminal or saves it as an image
+//! - `experiment` runs a campaign described by a TOML experiment file
+//!
+//! Tables are written as CSV, JSON Lines or NPY, chosen by `--format` or
+//! the extension of `--output`.
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use ising_lib::checkpoint::Checkpoint;
+use ising_lib::error::{check_finite, IsingError};
+use ising_lib::experiment::{Experiment, SUMMARY_COLUMNS};
+use ising_lib::image::{Image, Palette};
+use ising_lib::lattice2d::{Boundary, InitType, Lattice2d, UpdateRule};
+use ising_lib::measurement::Measurement;
+use ising_lib::monte_carlo_measurement::{MonteCarlo, MonteCarloParams};
+use ising_lib::observable::{Observable, Quantity};
+use ising_lib::output::{Format, TableWriter};
+use ising_lib::pool;
+use ising_lib::render::{Overlay, TerminalRenderer};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const OBSERVABLES: [&str; 4] = ["energy", "energy_per_spin", "magnetization", "nn_correlation"];
+
+#[derive(Parser)]
+#[command(name = "ising", version, about = "Ising model simulations on 2d lattices")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Evolve a single lattice and stream its observables every few sweeps
+    Run(RunArgs),
+    /// Monte Carlo sample a quantity over a grid of temperatures and fields
+    Scan(ScanArgs),
+    /// Observables of a saved configuration (a checkpoint or an image)
+    Measure(MeasureArgs),
+    /// Draw a configuration in the terminal or save it as an image
+    Render(RenderArgs),
+    /// Run a campaign described by a TOML experiment file
+    Experiment(ExperimentArgs),
+}
+
+#[derive(Args)]
+struct CouplingArgs {
+    /// Interaction constant J
+    #[arg(long, default_value_t = 1.0, allow_negative_numbers = true)]
+    j: f64,
+    /// External field h
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    h: f64,
+    /// Inverse temperature
+    #[arg(long, default_value_t = 0.43, conflicts_with = "temperature")]
+    beta: f64,
+    /// Temperature, instead of --beta
+    #[arg(long)]
+    temperature: Option<f64>,
+}
+
+impl CouplingArgs {
+    fn beta(&self) -> f64 {
+        self.temperature.map_or(self.beta, |t| 1.0 / t)
+    }
+}
+
+#[derive(Args)]
+struct LatticeArgs {
+    /// Lattice dimensions, ROWSxCOLS
+    #[arg(long, default_value = "25x25", value_parser = parse_dims)]
+    dims: [usize; 2],
+    #[arg(long, value_enum, default_value_t = RuleArg::Metropolis)]
+    update_rule: RuleArg,
+    /// Initial spins: random, all-up, all-down, checkerboard, domain-wall,
+    /// stripes:WIDTH or biased:P_UP
+    #[arg(long, default_value = "random", value_parser = parse_init)]
+    init: InitType,
+    /// Initial spins from an image (bright is up), sets the dims
+    #[arg(long, conflicts_with_all = ["init", "dims"])]
+    init_image: Option<PathBuf>,
+    /// Vacant sites from an image mask (dark is vacant)
+    #[arg(long)]
+    vacancies: Option<PathBuf>,
+    /// Pinned sites from an image mask (dark is pinned)
+    #[arg(long, conflicts_with = "boundary")]
+    pinned: Option<PathBuf>,
+    /// Gray level at which images are thresholded
+    #[arg(long, default_value_t = 128)]
+    threshold: u8,
+    #[arg(long, value_enum)]
+    boundary: Option<BoundaryArg>,
+    /// Seed of the random number generator
+    #[arg(long)]
+    seed: Option<u64>,
+    #[command(flatten)]
+    couplings: CouplingArgs,
+}
+
+#[derive(Args)]
+struct MonteCarloArgs {
+    /// Number of independent runs, each from freshly initialized spins
+    #[arg(long, default_value_t = 25)]
+    n_runs: usize,
+    /// Flips to skip at the start of each run
+    #[arg(long, default_value_t = 300_000)]
+    flips_to_skip: usize,
+    #[arg(long, default_value_t = 10)]
+    samples_per_run: usize,
+    #[arg(long, default_value_t = 30_000)]
+    flips_to_skip_between_samples: usize,
+    /// Run the Monte Carlo runs in parallel
+    #[arg(long)]
+    parallel: bool,
+    /// Number of worker threads with --parallel, one per CPU by default
+    #[arg(long, requires = "parallel")]
+    threads: Option<usize>,
+}
+
+impl MonteCarloArgs {
+    fn params(&self) -> Result<MonteCarloParams, IsingError> {
+        for (name, n) in [("n_runs", self.n_runs), ("samples_per_run", self.samples_per_run)] {
+            if n == 0 {
+                return Err(IsingError::InvalidParameter {
+                    name,
+                    reason: String::from("must be positive"),
+                });
+            }
+        }
+        Ok(MonteCarloParams {
+            n_runs: self.n_runs,
+            flips_to_skip: self.flips_to_skip,
+            samples_per_run: self.samples_per_run,
+            flips_to_skip_between_samples: self.flips_to_skip_between_samples,
+        })
+    }
+}
+
+#[derive(Args)]
+struct OutputArgs {
+    /// Output file, standard output by default
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+    /// Table format, by default from the output extension, else csv
+    #[arg(long, value_enum)]
+    format: Option<FormatArg>,
+}
+
+#[derive(Args)]
+struct RunArgs {
+    #[command(flatten)]
+    lattice: LatticeArgs,
+    /// Sweeps (n_sites attempted flips each) to run while recording
+    #[arg(long, default_value_t = 1000)]
+    sweeps: usize,
+    /// Sweeps to run before recording
+    #[arg(long, default_value_t = 0)]
+    equilibrate: usize,
+    /// Record every this many sweeps, and after the last sweep
+    #[arg(long, default_value_t = 1)]
+    every: usize,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Args)]
+struct ScanArgs {
+    #[command(flatten)]
+    lattice: LatticeArgs,
+    #[command(flatten)]
+    monte_carlo: MonteCarloArgs,
+    /// Temperatures, START:STOP:COUNT or a comma separated list, by
+    /// default the single temperature of --beta or --temperature
+    #[arg(long, value_parser = parse_grid)]
+    temperatures: Option<Grid>,
+    /// Fields, START:STOP:COUNT or a comma separated list, by default the
+    /// single field of --h
+    #[arg(long, value_parser = parse_grid, allow_hyphen_values = true)]
+    fields: Option<Grid>,
+    #[arg(long, value_enum, default_value_t = QuantityArg::Energy)]
+    quantity: QuantityArg,
+    /// Write every sample instead of the mean, standard deviation and
+    /// standard error (over run means) per grid point
+    #[arg(long)]
+    raw: bool,
+    /// Report each finished grid point on standard error
+    #[arg(long, short)]
+    verbose: bool,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Args)]
+struct MeasureArgs {
+    /// A checkpoint (.ckpt) or an image (bright is up)
+    input: PathBuf,
+    /// Gray level at which images are thresholded
+    #[arg(long, default_value_t = 128)]
+    threshold: u8,
+    /// Couplings of an image, checkpoints store their own
+    #[command(flatten)]
+    couplings: CouplingArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Args)]
+struct ExperimentArgs {
+    /// The experiment file
+    file: PathBuf,
+    /// Only parse and validate the file
+    #[arg(long)]
+    check: bool,
+    /// The summary also goes here, standard output if the file has no outputs
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Args)]
+struct RenderArgs {
+    /// A checkpoint (.ckpt) or an image, otherwise a new lattice
+    input: Option<PathBuf>,
+    #[command(flatten)]
+    lattice: LatticeArgs,
+    /// Sweeps to run before drawing
+    #[arg(long, default_value_t = 0)]
+    sweeps: usize,
+    #[arg(long, value_enum, default_value_t = OverlayArg::Spins)]
+    overlay: OverlayArg,
+    /// Save as an image (pbm, pgm or png) instead of drawing in the terminal
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+    /// Pixels per site of a saved image
+    #[arg(long, default_value_t = 1)]
+    scale: usize,
+    /// Draw without colors or half-blocks
+    #[arg(long)]
+    plain: bool,
+}
+
+/// Values of a scanned parameter
+#[derive(Clone)]
+struct Grid(Vec<f64>);
+
+#[derive(Clone, Copy, ValueEnum)]
+enum RuleArg {
+    Metropolis,
+    Glauber,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum BoundaryArg {
+    Periodic,
+    FixedUp,
+    Interface,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum QuantityArg {
+    Energy,
+    NnCorrelation,
+    Magnetization,
+    AbsMagnetization,
+    MagnetizationSquared,
+    MagnetizationQuartic,
+    StaggeredMagnetization,
+    AbsStaggeredMagnetization,
+    DomainWallLength,
+    InterfaceWidth,
+}
+
+impl From<QuantityArg> for Quantity {
+    fn from(arg: QuantityArg) -> Self {
+        match arg {
+            QuantityArg::Energy => Quantity::Energy,
+            QuantityArg::NnCorrelation => Quantity::NeighborCorrelation,
+            QuantityArg::Magnetization => Quantity::Magnetization,
+            QuantityArg::AbsMagnetization => Quantity::AbsMagnetization,
+            QuantityArg::MagnetizationSquared => Quantity::MagnetizationSquared,
+            QuantityArg::MagnetizationQuartic => Quantity::MagnetizationQuartic,
+            QuantityArg::StaggeredMagnetization => Quantity::StaggeredMagnetization,
+            QuantityArg::AbsStaggeredMagnetization => Quantity::AbsStaggeredMagnetization,
+            QuantityArg::DomainWallLength => Quantity::DomainWallLength,
+            QuantityArg::InterfaceWidth => Quantity::InterfaceWidth,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FormatArg {
+    Csv,
+    Jsonl,
+    Npy,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OverlayArg {
+    Spins,
+    Energy,
+}
+
+fn parse_dims(s: &str) -> Result<[usize; 2], String> {
+    let (rows, cols) = s.split_once('x').ok_or("expected ROWSxCOLS")?;
+    let parse = |n: &str| n.trim().parse::<usize>().map_err(|err| format!("{}: {}", n, err));
+    Ok([parse(rows)?, parse(cols)?])
+}
+
+fn parse_init(s: &str) -> Result<InitType, String> {
+    let (name, arg) = match s.split_once(':') {
+        Some((name, arg)) => (name, Some(arg)),
+        None => (s, None),
+    };
+    match (name, arg) {
+        ("random", None) => Ok(InitType::Random),
+        ("all-up", None) => Ok(InitType::AllUp),
+        ("all-down", None) => Ok(InitType::AllDown),
+        ("checkerboard", None) => Ok(InitType::Checkerboard),
+        ("domain-wall", None) => Ok(InitType::DomainWall),
+        ("stripes", Some(width)) => width
+            .parse()
+            .map(|width| InitType::Stripes { width })
+            .map_err(|err| format!("stripe width {}: {}", width, err)),
+        ("biased", Some(p_up)) => p_up
+            .parse()
+            .map(|p_up| InitType::RandomBiased { p_up })
+            .map_err(|err| format!("probability {}: {}", p_up, err)),
+        _ => Err(format!("unknown initial condition {}", s)),
+    }
+}
+
+/// START:STOP:COUNT (inclusive, evenly spaced) or a comma separated list
+fn parse_grid(s: &str) -> Result<Grid, String> {
+    let parse = |x: &str| match x.trim().parse::<f64>() {
+        Ok(x) if x.is_finite() => Ok(x),
+        Ok(x) => Err(format!("{} is not finite", x)),
+        Err(err) => Err(format!("{}: {}", x, err)),
+    };
+    let parts: Vec<&str> = s.split(':').collect();
+    match parts.as_slice() {
+        [start, stop, count] => {
+            let (start, stop) = (parse(start)?, parse(stop)?);
+            let count: usize = count.trim().parse().map_err(|err| format!("{}: {}", count, err))?;
+            match count {
+                0 => Err(String::from("the count must be positive")),
+                1 => Ok(Grid(vec![start])),
+                _ => Ok(Grid(
+                    (0..count)
+                        .map(|i| start + (stop - start) * i as f64 / (count - 1) as f64)
+                        .collect(),
+                )),
+            }
+        }
+        [list] => list.split(',').map(parse).collect::<Result<_, _>>().map(Grid),
+        _ => Err(String::from("expected START:STOP:COUNT or a comma separated list")),
+    }
+}
+
+impl LatticeArgs {
+    fn build(&self) -> Result<Lattice2d, IsingError> {
+        let mut builder = Lattice2d::builder()
+            .dims(self.dims)
+            .update_rule(match self.update_rule {
+                RuleArg::Metropolis => UpdateRule::Metropolis,
+                RuleArg::Glauber => UpdateRule::Glauber,
+            })
+            .init_type(self.init.clone())
+            .j(self.couplings.j)
+            .h(self.couplings.h)
+            .beta(self.couplings.beta());
+        if let Some(path) = &self.init_image {
+            builder = builder.init_array(Image::load(path)?.to_spins(self.threshold));
+        }
+        if let Some(seed) = self.seed {
+            builder = builder.seed(seed);
+        }
+        if let Some(path) = &self.vacancies {
+            builder = builder.vacancies(Image::load(path)?.to_mask(self.threshold));
+        }
+        if let Some(path) = &self.pinned {
+            builder = builder.pinned(Image::load(path)?.to_mask(self.threshold));
+        }
+        let mut lattice = builder.build()?;
+        if let Some(boundary) = self.boundary {
+            lattice.set_boundary(match boundary {
+                BoundaryArg::Periodic => Boundary::Periodic,
+                BoundaryArg::FixedUp => Boundary::FixedUp,
+                BoundaryArg::Interface => Boundary::Interface,
+            });
+        }
+        Ok(lattice)
+    }
+}
+
+impl OutputArgs {
+    fn table(&self, columns: &[&str], n_rows: usize) -> Result<TableWriter<Box<dyn Write>>, IsingError> {
+        let format = match (self.format, &self.output) {
+            (Some(FormatArg::Csv), _) => Format::Csv,
+            (Some(FormatArg::Jsonl), _) => Format::JsonLines,
+            (Some(FormatArg::Npy), _) => Format::Npy,
+            (None, Some(path)) => Format::from_path(path)?,
+            (None, None) => Format::Csv,
+        };
+        let w: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(io::stdout().lock()),
+        };
+        TableWriter::new(w, format, columns, n_rows)
+    }
+}
+
+/// Loads a checkpoint by its `ckpt` extension, anything else as an image
+fn load_configuration(path: &Path, threshold: u8, couplings: &CouplingArgs) -> Result<Lattice2d, IsingError> {
+    if path.extension().is_some_and(|ext| ext == "ckpt") {
+        let mut r = BufReader::new(File::open(path)?);
+        return Ok(Checkpoint::read_from(&mut r)?.lattice);
+    }
+    Lattice2d::builder()
+        .init_array(Image::load(path)?.to_spins(threshold))
+        .j(couplings.j)
+        .h(couplings.h)
+        .beta(couplings.beta())
+        .build()
+}
+
+fn observables(lattice: &Lattice2d) -> [f64; 4] {
+    [
+        lattice.measure_energy(),
+        lattice.measure_energy_per_spin(),
+        lattice.get_spin_mean(),
+        Quantity::NeighborCorrelation.measure(lattice),
+    ]
+}
+
+fn run(args: &RunArgs) -> Result<(), IsingError> {
+    if args.every == 0 {
+        return Err(IsingError::InvalidParameter {
+            name: "every",
+            reason: String::from("must be positive"),
+        });
+    }
+    let mut lattice = args.lattice.build()?;
+    let sweep = lattice.n_sites;
+    lattice.update_n(args.equilibrate * sweep);
+    let mut columns = vec!["sweep"];
+    columns.extend(OBSERVABLES);
+    let recorded = recorded_sweeps(args.sweeps, args.every);
+    let mut table = args.output.table(&columns, recorded.len())?;
+    let mut done = 0;
+    for n in recorded {
+        lattice.update_n((n - done) * sweep);
+        done = n;
+        let mut row = vec![n as f64];
+        row.extend(observables(&lattice));
+        table.write_row(&row)?;
+    }
+    table.finish()?;
+    Ok(())
+}
+
+/// The sweeps after which `run` records, every `every` sweeps from 0 and
+/// the last one even if `sweeps` isn't a multiple of `every`
+fn recorded_sweeps(sweeps: usize, every: usize) -> Vec<usize> {
+    let mut recorded: Vec<usize> = (0..=sweeps).step_by(every).collect();
+    if recorded.last() != Some(&sweeps) {
+        recorded.push(sweeps);
+    }
+    recorded
+}
+
+fn scan(args: &ScanArgs) -> Result<(), IsingError> {
+    let mut lattice = args.lattice.build()?;
+    let params = args.monte_carlo.params()?;
+    let n_threads = args.monte_carlo.threads.unwrap_or_else(pool::default_n_threads);
+    let temperatures = args.temperatures.clone().map_or(vec![1.0 / lattice.beta], |grid| grid.0);
+    let fields = args.fields.clone().map_or(vec![lattice.h], |grid| grid.0);
+    if let Some(t) = temperatures.iter().find(|t| !(t.is_finite() && **t > 0.0)) {
+        return Err(IsingError::InvalidParameter {
+            name: "temperatures",
+            reason: format!("{} is not a positive temperature", t),
+        });
+    }
+    let n_points = temperatures.len() * fields.len();
+    let mut table = if args.raw {
+        let columns = ["temperature", "beta", "h", "run", "sample", "value"];
+        args.output.table(&columns, n_points * params.n_runs * params.samples_per_run)?
+    } else {
+        let columns = ["temperature", "beta", "h", "mean", "std", "sem", "n_samples"];
+        args.output.table(&columns, n_points)?
+    };
+    for &temperature in temperatures.iter() {
+        for &h in fields.iter() {
+            let beta = 1.0 / temperature;
+            // the fields bypass the builder, so check them as it would
+            check_finite("h", h)?;
+            lattice.beta = beta;
+            lattice.h = h;
+            let samples = match (args.quantity, args.monte_carlo.parallel) {
+                (QuantityArg::Energy, false) => lattice.sample_energy(&params),
+                (QuantityArg::Energy, true) => lattice.sample_energy_parallel(&params, n_threads)?,
+                (QuantityArg::NnCorrelation, false) => lattice.sample_neighbor_correlations(&params),
+                (QuantityArg::NnCorrelation, true) => lattice.sample_neighbor_correlations_parallel(&params, n_threads)?,
+                (QuantityArg::Magnetization, false) => lattice.sample_magnetization(&params),
+                (QuantityArg::Magnetization, true) => lattice.sample_magnetization_parallel(&params, n_threads)?,
+                (quantity, false) => lattice.sample_quantity(quantity.into(), &params),
+                (quantity, true) => lattice.sample_quantity_parallel(quantity.into(), &params, n_threads)?,
+            };
+            if args.verbose {
+                eprintln!("T={} h={} done", temperature, h);
+            }
+            if args.raw {
+                for (run, values) in samples.samples.iter().enumerate() {
+                    for (sample, &value) in values.iter().enumerate() {
+                        table.write_row(&[temperature, beta, h, run as f64, sample as f64, value])?;
+                    }
+                }
+            } else {
+                let (mean, std, sem) = samples.mean_std_sem();
+                let n_samples = samples.n_samples();
+                table.write_row(&[temperature, beta, h, mean, std, sem, n_samples as f64])?;
+            }
+        }
+    }
+    table.finish()?;
+    Ok(())
+}
+
+fn measure(args: &MeasureArgs) -> Result<(), IsingError> {
+    let lattice = load_configuration(&args.input, args.threshold, &args.couplings)?;
+    let mut columns = vec!["rows", "cols"];
+    columns.extend(OBSERVABLES);
+    let mut table = args.output.table(&columns, 1)?;
+    let mut row = vec![lattice.dims[0] as f64, lattice.dims[1] as f64];
+    row.extend(observables(&lattice));
+    table.write_row(&row)?;
+    table.finish()?;
+    Ok(())
+}
+
+fn render(args: &RenderArgs) -> Result<(), IsingError> {
+    let mut lattice = match &args.input {
+        Some(path) => load_configuration(path, args.lattice.threshold, &args.lattice.couplings)?,
+        None => args.lattice.build()?,
+    };
+    lattice.update_n(args.sweeps * lattice.n_sites);
+    let overlay = match args.overlay {
+        OverlayArg::Spins => Overlay::Spins,
+        OverlayArg::Energy => Overlay::Energy,
+    };
+    match &args.output {
+        Some(path) => {
+            let image = match args.overlay {
+                OverlayArg::Spins => Image::from_spins(&lattice.nodes, &Palette::Grayscale, args.scale),
+                OverlayArg::Energy => {
+                    let energy = lattice.measure_local_energy();
+                    let e_max = 4.0 * lattice.j.abs() + lattice.h.abs();
+                    Image::from_field(energy.view(), (-e_max, e_max), &Palette::diverging(), args.scale)
+                }
+            };
+            image.save(path)
+        }
+        None => {
+            let mut renderer = if args.plain { TerminalRenderer::plain() } else { TerminalRenderer::new() };
+            renderer.render(&lattice, &overlay, &mut io::stdout().lock())?;
+            Ok(())
+        }
+    }
+}
+
+fn experiment(args: &ExperimentArgs) -> Result<(), IsingError> {
+    let experiment = Experiment::load(&args.file)?;
+    if args.check {
+        eprintln!("{} is valid", args.file.display());
+        return Ok(());
+    }
+    let results = experiment.run()?;
+    let has_outputs = experiment.output.summary.is_some() || experiment.output.samples.is_some();
+    if args.output.output.is_some() || !has_outputs {
+        let mut table = args.output.table(&SUMMARY_COLUMNS, results.len())?;
+        experiment.write_summary(&results, &mut table)?;
+        table.finish()?;
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match &cli.command {
+        Command::Run(args) => run(args),
+        Command::Scan(args) => scan(args),
+        Command::Measure(args) => measure(args),
+        Command::Render(args) => render(args),
+        Command::Experiment(args) => experiment(args),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+        let cli = Cli::try_parse_from(["ising", "scan", "--temperatures", "1:2:3", "--fields=-1,1"]).unwrap();
+        match cli.command {
+            Command::Scan(args) => {
+                assert_eq!(args.temperatures.unwrap().0, vec![1.0, 1.5, 2.0]);
+                assert_eq!(args.fields.unwrap().0, vec![-1.0, 1.0]);
+            }
+            _ => panic!("expected scan"),
+        }
+        assert!(Cli::try_parse_from(["ising", "run", "--beta", "1", "--temperature", "1"]).is_err());
+        assert!(Cli::try_parse_from(["ising", "scan", "--fields", "0,inf"]).is_err());
+        for arg in ["--n-runs", "--samples-per-run"] {
+            let Command::Scan(args) = Cli::try_parse_from(["ising", "scan", arg, "0"]).unwrap().command else {
+                panic!("expected scan");
+            };
+            assert!(matches!(scan(&args), Err(IsingError::InvalidParameter { .. })));
+        }
+    }
+
+    #[test]
+    fn test_parsers() {
+        assert_eq!(parse_dims("16x32").unwrap(), [16, 32]);
+        assert!(parse_dims("16").is_err());
+        assert!(matches!(parse_init("stripes:3"), Ok(InitType::Stripes { width: 3 })));
+        assert!(matches!(parse_init("biased:0.25"), Ok(InitType::RandomBiased { p_up }) if p_up == 0.25));
+        assert!(parse_init("stripes").is_err());
+        assert!(parse_grid("1:2:0").is_err());
+        assert_eq!(parse_grid("0.5").unwrap().0, vec![0.5]);
+        assert!(parse_grid("0,nan").is_err());
+        assert!(parse_grid("1:inf:3").is_err());
+    }
+
+    #[test]
+    fn test_recorded_sweeps() {
+        assert_eq!(recorded_sweeps(6, 2), vec![0, 2, 4, 6]);
+        assert_eq!(recorded_sweeps(7, 2), vec![0, 2, 4, 6, 7]);
+        assert_eq!(recorded_sweeps(3, 10), vec![0, 3]);
+        assert_eq!(recorded_sweeps(0, 1), vec![0]);
+    }
+}
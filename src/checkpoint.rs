@@ -0,0 +1,634 @@
+//! Checkpointing of long Monte Carlo campaigns. A checkpointed run
+//! periodically writes the lattice state, the state of its random number
+//! generator, the samples taken so far and the parameters to a compact
+//! binary file. If the run is interrupted it can be resumed from that
+//! file, and continues bit-for-bit as if it had never stopped. The
+//! samples are the same as those of `MonteCarlo::sample_quantity`.
+//!
+//! ```no_run
+//! use ising_lib::checkpoint::{resume, CheckpointConfig, Quantity};
+//! use ising_lib::lattice2d::Lattice2d;
+//! use ising_lib::monte_carlo_measurement::MonteCarloParams;
+//!
+//! let params = MonteCarloParams {
+//!     n_runs: 25,
+//!     flips_to_skip: 300_000,
+//!     samples_per_run: 10,
+//!     flips_to_skip_between_samples: 30_000,
+//! };
+//! let config = CheckpointConfig::new("nn_corr.ckpt", 10);
+//! let mut lattice = Lattice2d::builder().seed(1).build().unwrap();
+//! let samples = lattice.sample_checkpointed(Quantity::NeighborCorrelation, &params, &config);
+//!
+//! // ...or, after an interruption
+//! let (lattice, samples) = resume(&config).unwrap();
+//! ```
+
+use crate::error::IsingError;
+use crate::lattice2d::*;
+use crate::observable::Observable;
+pub use crate::observable::Quantity;
+use crate::monte_carlo_measurement::{continue_run, run_seeds, start_run, MonteCarloParams};
+use ndarray::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+
+const MAGIC: &[u8; 8] = b"ISNGCKPT";
+const VERSION: u32 = 3;
+
+/// Where and how often to write checkpoints
+pub struct CheckpointConfig {
+    pub path: PathBuf,
+    pub every_n_samples: usize, // write a checkpoint after this many samples
+}
+
+impl CheckpointConfig {
+    pub fn new<P: Into<PathBuf>>(path: P, every_n_samples: usize) -> Self {
+        CheckpointConfig {
+            path: path.into(),
+            every_n_samples,
+        }
+    }
+}
+
+/// Everything needed to continue an interrupted run
+pub struct Checkpoint {
+    pub quantity: Quantity,
+    pub params: MonteCarloParams,
+    pub lattice: Lattice2d,
+    /// Samples taken so far, one vec per started run, the last may be partial
+    pub samples: Vec<Vec<f64>>,
+    /// The seed of each run, drawn up front like the `MonteCarlo` samplers do
+    pub seeds: Vec<u64>,
+}
+
+impl Checkpoint {
+    /// Writes the checkpoint to a file, via a temporary file so that an
+    /// interruption while writing never leaves a corrupt checkpoint behind
+    pub fn save(&self, config: &CheckpointConfig) -> Result<(), IsingError> {
+        let mut tmp_path = config.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        {
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            self.write_to(&mut writer)?;
+            writer.flush()?;
+        }
+        fs::rename(&tmp_path, &config.path)?;
+        Ok(())
+    }
+
+    /// Reads a checkpoint from a file
+    pub fn load(config: &CheckpointConfig) -> Result<Self, IsingError> {
+        let mut reader = BufReader::new(File::open(&config.path)?);
+        Checkpoint::read_from(&mut reader)
+    }
+
+    /// Encodes the checkpoint, all numbers are little endian and the
+    /// spins and site masks are packed into bits
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<(), IsingError> {
+        w.write_all(MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        let quantity: u8 = match self.quantity {
+            Quantity::Energy => 0,
+            Quantity::NeighborCorrelation => 1,
+            Quantity::Magnetization => 2,
+            Quantity::AbsMagnetization => 3,
+            Quantity::MagnetizationSquared => 4,
+            Quantity::MagnetizationQuartic => 5,
+            Quantity::StaggeredMagnetization => 6,
+            Quantity::AbsStaggeredMagnetization => 7,
+            Quantity::DomainWallLength => 8,
+            Quantity::InterfaceWidth => 9,
+        };
+        w.write_all(&[quantity])?;
+        for n in [
+            self.params.n_runs,
+            self.params.flips_to_skip,
+            self.params.samples_per_run,
+            self.params.flips_to_skip_between_samples,
+        ] {
+            write_u64(w, n as u64)?;
+        }
+
+        let lattice = &self.lattice;
+        write_u64(w, lattice.dims[0] as u64)?;
+        write_u64(w, lattice.dims[1] as u64)?;
+        let update_rule: u8 = match lattice.update_rule {
+            UpdateRule::Metropolis => 0,
+            UpdateRule::Glauber => 1,
+        };
+        let spin_type: u8 = match lattice.spin_type {
+            SpinType::SpinHalf => 0,
+        };
+        w.write_all(&[update_rule, spin_type])?;
+        match &lattice.init_type {
+            InitType::Random => w.write_all(&[0])?,
+            InitType::AllUp => w.write_all(&[1])?,
+            InitType::AllDown => w.write_all(&[2])?,
+            InitType::Checkerboard => w.write_all(&[3])?,
+            InitType::Stripes { width } => {
+                w.write_all(&[4])?;
+                write_u64(w, *width as u64)?;
+            }
+            InitType::DomainWall => w.write_all(&[5])?,
+            InitType::RandomBiased { p_up } => {
+                w.write_all(&[6])?;
+                write_f64(w, *p_up)?;
+            }
+            InitType::FromArray(arr) => {
+                w.write_all(&[7])?;
+                write_spins(w, arr)?;
+            }
+        }
+        for x in [lattice.j, lattice.h, lattice.beta] {
+            write_f64(w, x)?;
+        }
+        let vacancies = lattice.vacancies();
+        write_spins(w, &lattice.nodes.mapv(|s| if s == 0 { 1 } else { s }))?;
+        for mask in [vacancies, lattice.pinned()] {
+            match mask {
+                Some(mask) => {
+                    w.write_all(&[1])?;
+                    write_spins(w, &mask.mapv(|x| if x { 1 } else { -1 }))?;
+                }
+                None => w.write_all(&[0])?,
+            }
+        }
+
+        w.write_all(&lattice.rng.get_seed())?;
+        write_u64(w, lattice.rng.get_stream())?;
+        w.write_all(&lattice.rng.get_word_pos().to_le_bytes())?;
+
+        write_u64(w, self.samples.len() as u64)?;
+        for run in self.samples.iter() {
+            write_u64(w, run.len() as u64)?;
+            for &x in run.iter() {
+                write_f64(w, x)?;
+            }
+        }
+        for &seed in self.seeds.iter() {
+            write_u64(w, seed)?;
+        }
+        Ok(())
+    }
+
+    /// Decodes a checkpoint written by `write_to`
+    pub fn read_from<R: Read>(r: &mut R) -> Result<Self, IsingError> {
+        let magic: [u8; 8] = read_array(r)?;
+        if &magic != MAGIC {
+            return Err(IsingError::Format("not an ising_lib checkpoint".to_owned()));
+        }
+        let version: [u8; 4] = read_array(r)?;
+        if u32::from_le_bytes(version) != VERSION {
+            return Err(IsingError::Format(format!(
+                "unsupported checkpoint version {}",
+                u32::from_le_bytes(version)
+            )));
+        }
+        let quantity = match read_u8(r)? {
+            0 => Quantity::Energy,
+            1 => Quantity::NeighborCorrelation,
+            2 => Quantity::Magnetization,
+            3 => Quantity::AbsMagnetization,
+            4 => Quantity::MagnetizationSquared,
+            5 => Quantity::MagnetizationQuartic,
+            6 => Quantity::StaggeredMagnetization,
+            7 => Quantity::AbsStaggeredMagnetization,
+            8 => Quantity::DomainWallLength,
+            9 => Quantity::InterfaceWidth,
+            tag => return Err(bad_tag("quantity", tag)),
+        };
+        let params = MonteCarloParams {
+            n_runs: read_usize(r)?,
+            flips_to_skip: read_usize(r)?,
+            samples_per_run: read_usize(r)?,
+            flips_to_skip_between_samples: read_usize(r)?,
+        };
+
+        let dims = [read_usize(r)?, read_usize(r)?];
+        if dims.contains(&0) {
+            return Err(IsingError::Format(format!("invalid dims {:?}", dims)));
+        }
+        let update_rule = match read_u8(r)? {
+            0 => UpdateRule::Metropolis,
+            1 => UpdateRule::Glauber,
+            tag => return Err(bad_tag("update rule", tag)),
+        };
+        let spin_type = match read_u8(r)? {
+            0 => SpinType::SpinHalf,
+            tag => return Err(bad_tag("spin type", tag)),
+        };
+        let init_type = match read_u8(r)? {
+            0 => InitType::Random,
+            1 => InitType::AllUp,
+            2 => InitType::AllDown,
+            3 => InitType::Checkerboard,
+            4 => InitType::Stripes { width: read_usize(r)? },
+            5 => InitType::DomainWall,
+            6 => InitType::RandomBiased { p_up: read_f64(r)? },
+            7 => InitType::FromArray(read_spins(r, dims)?),
+            tag => return Err(bad_tag("initial condition", tag)),
+        };
+        let (j, h, beta) = (read_f64(r)?, read_f64(r)?, read_f64(r)?);
+        check_couplings(j, h, beta)?;
+        let mut nodes = read_spins(r, dims)?;
+        let mut masks = [None, None];
+        for mask in masks.iter_mut() {
+            *mask = match read_u8(r)? {
+                0 => None,
+                1 => Some(read_spins(r, dims)?.mapv(|s| s == 1)),
+                tag => return Err(bad_tag("mask", tag)),
+            };
+        }
+        let [vacancies, pinned] = masks;
+        if let Some(vacancies) = &vacancies {
+            nodes.zip_mut_with(vacancies, |s, &vacant| if vacant { *s = 0 });
+        }
+
+        let seed: [u8; 32] = read_array(r)?;
+        let stream = read_u64(r)?;
+        let word_pos: [u8; 16] = read_array(r)?;
+        let mut rng = ChaCha8Rng::from_seed(seed);
+        rng.set_stream(stream);
+        rng.set_word_pos(u128::from_le_bytes(word_pos));
+
+        let n_started = read_usize(r)?;
+        if n_started > params.n_runs {
+            return Err(IsingError::Format("more runs than n_runs".to_owned()));
+        }
+        // the sizes come from the file, so the vecs only grow as the
+        // samples are actually read
+        let mut samples = vec![];
+        for _ in 0..n_started {
+            let len = read_usize(r)?;
+            if len > params.samples_per_run {
+                return Err(IsingError::Format("more samples than samples_per_run".to_owned()));
+            }
+            let mut run = vec![];
+            for _ in 0..len {
+                run.push(read_f64(r)?);
+            }
+            samples.push(run);
+        }
+        let mut seeds = vec![];
+        for _ in 0..params.n_runs {
+            seeds.push(read_u64(r)?);
+        }
+
+        init_type.validate(&dims)?;
+        let mut lattice = Lattice2d::new(dims, update_rule, spin_type, init_type, j, h, beta);
+        lattice.set_vacancies(vacancies)?;
+        lattice.set_pinned(pinned)?;
+        lattice.nodes = nodes;
+        lattice.resync_totals();
+        lattice.rng = rng;
+        Ok(Checkpoint {
+            quantity,
+            params,
+            lattice,
+            samples,
+            seeds,
+        })
+    }
+}
+
+/// Checkpointed Monte Carlo sampling for the Lattice2d type
+impl Lattice2d {
+    /// Samples a quantity like the serial `MonteCarlo` samplers, writing a
+    /// checkpoint every `config.every_n_samples` samples and once at the end.
+    /// Returns a vec of samples per run.
+    pub fn sample_checkpointed(
+        &mut self,
+        quantity: Quantity,
+        params: &MonteCarloParams,
+        config: &CheckpointConfig,
+    ) -> Result<Vec<Vec<f64>>, IsingError> {
+        let seeds = run_seeds(self, params);
+        // the runs reseed the lattice, leave its rng as the samplers do
+        let rng = self.rng.clone();
+        let mut samples = vec![];
+        self.continue_checkpointed(quantity, params, &seeds, config, &mut samples, None)?;
+        self.rng = rng;
+        Ok(samples)
+    }
+
+    /// Runs (or continues) the runs of the `MonteCarlo` samplers one
+    /// sample at a time, taking at most `max_samples` samples if given.
+    /// Each run starts from its own seed and the state after each sample
+    /// fully determines the rest of the run, which is what makes resuming
+    /// exact.
+    fn continue_checkpointed(
+        &mut self,
+        quantity: Quantity,
+        params: &MonteCarloParams,
+        seeds: &[u64],
+        config: &CheckpointConfig,
+        samples: &mut Vec<Vec<f64>>,
+        max_samples: Option<usize>,
+    ) -> Result<(), IsingError> {
+        let mut taken = 0;
+        loop {
+            let run_done = samples
+                .last()
+                .is_none_or(|run| run.len() == params.samples_per_run);
+            if run_done {
+                if samples.len() == params.n_runs {
+                    break;
+                }
+                start_run(self, params, seeds[samples.len()]);
+                samples.push(Vec::with_capacity(params.samples_per_run));
+                continue;
+            }
+            if max_samples == Some(taken) {
+                return Ok(()); // stop without a final checkpoint, like an interruption
+            }
+            let run = samples.last_mut().unwrap();
+            continue_run(self, params, 1, |lattice| run.push(quantity.measure(lattice)));
+            taken += 1;
+            if config.every_n_samples > 0 && taken % config.every_n_samples == 0 {
+                self.save_checkpoint(quantity, params, seeds, config, samples)?;
+            }
+        }
+        self.save_checkpoint(quantity, params, seeds, config, samples)
+    }
+
+    fn save_checkpoint(
+        &self,
+        quantity: Quantity,
+        params: &MonteCarloParams,
+        seeds: &[u64],
+        config: &CheckpointConfig,
+        samples: &[Vec<f64>],
+    ) -> Result<(), IsingError> {
+        let checkpoint = Checkpoint {
+            quantity,
+            params: *params,
+            lattice: self.clone_exact(),
+            samples: samples.to_vec(),
+            seeds: seeds.to_vec(),
+        };
+        checkpoint.save(config)
+    }
+
+    /// A copy with the same spins and random number generator state, unlike
+    /// `clone` which re-initializes the spins
+    fn clone_exact(&self) -> Lattice2d {
+        Lattice2d {
+            dims: self.dims,
+            n_sites: self.n_sites,
+            nodes: self.nodes.clone(),
+            spin_sum: self.spin_sum,
+            dot_spin_neighbours: self.dot_spin_neighbours,
+            rng: self.rng.clone(),
+            update_rule: self.update_rule,
+            spin_type: self.spin_type,
+            init_type: self.init_type.clone(),
+            j: self.j,
+            h: self.h,
+            beta: self.beta,
+            vacancies: self.vacancies.clone(),
+            pinned: self.pinned.clone(),
+        }
+    }
+}
+
+/// Resumes an interrupted checkpointed run from `config.path`, continuing
+/// to write checkpoints there. Returns the lattice in its final state and
+/// all the samples, identical to those of an uninterrupted run.
+pub fn resume(config: &CheckpointConfig) -> Result<(Lattice2d, Vec<Vec<f64>>), IsingError> {
+    let Checkpoint {
+        quantity,
+        params,
+        mut lattice,
+        mut samples,
+        seeds,
+    } = Checkpoint::load(config)?;
+    lattice.continue_checkpointed(quantity, &params, &seeds, config, &mut samples, None)?;
+    Ok((lattice, samples))
+}
+
+fn bad_tag(what: &str, tag: u8) -> IsingError {
+    IsingError::Format(format!("unknown {} tag {}", what, tag))
+}
+
+fn write_u64<W: Write>(w: &mut W, x: u64) -> Result<(), IsingError> {
+    Ok(w.write_all(&x.to_le_bytes())?)
+}
+
+fn write_f64<W: Write>(w: &mut W, x: f64) -> Result<(), IsingError> {
+    Ok(w.write_all(&x.to_le_bytes())?)
+}
+
+/// Packs the spins row by row into bits, 1 for up and 0 for down
+fn write_spins<W: Write>(w: &mut W, spins: &Array2<i32>) -> Result<(), IsingError> {
+    let mut bytes = vec![0u8; spins.len().div_ceil(8)];
+    for (i, &s) in spins.iter().enumerate() {
+        if s == 1 {
+            bytes[i / 8] |= 1 << (i % 8);
+        }
+    }
+    Ok(w.write_all(&bytes)?)
+}
+
+fn truncated() -> IsingError {
+    IsingError::Format("checkpoint is truncated".to_owned())
+}
+
+/// Reads exactly N bytes, running out of data is a format error
+fn read_array<R: Read, const N: usize>(r: &mut R) -> Result<[u8; N], IsingError> {
+    let mut buf = [0u8; N];
+    r.read_exact(&mut buf).map_err(|err| match err.kind() {
+        io::ErrorKind::UnexpectedEof => truncated(),
+        _ => err.into(),
+    })?;
+    Ok(buf)
+}
+
+fn read_u8<R: Read>(r: &mut R) -> Result<u8, IsingError> {
+    Ok(read_array::<R, 1>(r)?[0])
+}
+
+fn read_u64<R: Read>(r: &mut R) -> Result<u64, IsingError> {
+    Ok(u64::from_le_bytes(read_array(r)?))
+}
+
+fn read_usize<R: Read>(r: &mut R) -> Result<usize, IsingError> {
+    usize::try_from(read_u64(r)?).map_err(|_| IsingError::Format("size out of range".to_owned()))
+}
+
+fn read_f64<R: Read>(r: &mut R) -> Result<f64, IsingError> {
+    Ok(f64::from_le_bytes(read_array(r)?))
+}
+
+fn read_spins<R: Read>(r: &mut R, dims: [usize; 2]) -> Result<Array2<i32>, IsingError> {
+    let n = dims[0]
+        .checked_mul(dims[1])
+        .ok_or_else(|| IsingError::Format("dims too large".to_owned()))?;
+    // don't trust the header with the allocation, read what is there
+    let n_bytes = n.div_ceil(8);
+    let mut bytes = vec![];
+    r.take(n_bytes as u64).read_to_end(&mut bytes)?;
+    if bytes.len() < n_bytes {
+        return Err(truncated());
+    }
+    Ok(Array2::from_shape_fn(dims, |(i, j)| {
+        let k = i * dims[1] + j;
+        if bytes[k / 8] & (1 << (k % 8)) != 0 {
+            1
+        } else {
+            -1
+        }
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::monte_carlo_measurement::MonteCarlo;
+
+    fn temp_config(name: &str, every_n_samples: usize) -> CheckpointConfig {
+        let path = std::env::temp_dir().join(format!("ising_lib_{}_{}.ckpt", name, std::process::id()));
+        CheckpointConfig::new(path, every_n_samples)
+    }
+
+    fn params() -> MonteCarloParams {
+        MonteCarloParams {
+            n_runs: 3,
+            flips_to_skip: 500,
+            samples_per_run: 5,
+            flips_to_skip_between_samples: 50,
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let config = temp_config("roundtrip", 1);
+        let mut lattice = Lattice2d::builder()
+            .dims([5, 9])
+            .init_type(InitType::Stripes { width: 2 })
+            .vacancies(Array2::from_shape_fn((5, 9), |(i, j)| i == j))
+            .pinned(Array2::from_shape_fn((5, 9), |(i, _)| i == 4))
+            .seed(3)
+            .build()
+            .unwrap();
+        lattice.update_n(100);
+        let checkpoint = Checkpoint {
+            quantity: Quantity::Magnetization,
+            params: params(),
+            lattice: lattice.clone_exact(),
+            samples: vec![vec![0.5, -0.25], vec![1.0]],
+            seeds: vec![7, 8, 9],
+        };
+        checkpoint.save(&config).unwrap();
+        let loaded = Checkpoint::load(&config).unwrap();
+        fs::remove_file(&config.path).unwrap();
+
+        assert_eq!(loaded.quantity, Quantity::Magnetization);
+        assert_eq!(loaded.params, params());
+        assert_eq!(loaded.samples, checkpoint.samples);
+        assert_eq!(loaded.seeds, checkpoint.seeds);
+        assert_eq!(loaded.lattice.nodes, lattice.nodes);
+        assert_eq!(loaded.lattice.dims, [5, 9]);
+        assert_eq!(loaded.lattice.vacancies(), lattice.vacancies());
+        assert_eq!(loaded.lattice.pinned(), lattice.pinned());
+        let mut resumed = loaded.lattice;
+        resumed.update_n(1_000);
+        lattice.update_n(1_000);
+        assert_eq!(resumed.nodes, lattice.nodes);
+    }
+
+    #[test]
+    fn test_resume_is_bit_for_bit() {
+        for quantity in [
+            Quantity::Energy,
+            Quantity::NeighborCorrelation,
+            Quantity::Magnetization,
+            Quantity::AbsStaggeredMagnetization,
+        ] {
+            let build = || Lattice2d::builder().dims([6, 6]).seed(11).build().unwrap();
+
+            let config = temp_config("uninterrupted", 2);
+            let expected = build().sample_checkpointed(quantity, &params(), &config).unwrap();
+            fs::remove_file(&config.path).unwrap();
+            assert_eq!(expected, build().sample_quantity(quantity, &params()).samples);
+
+            // interrupt after 7 samples, i.e. in the middle of the second run
+            let config = temp_config("interrupted", 2);
+            let mut lattice = build();
+            let seeds = run_seeds(&mut lattice, &params());
+            let mut samples = vec![];
+            lattice
+                .continue_checkpointed(quantity, &params(), &seeds, &config, &mut samples, Some(7))
+                .unwrap();
+            let (_, resumed) = resume(&config).unwrap();
+            fs::remove_file(&config.path).unwrap();
+
+            assert_eq!(resumed.len(), params().n_runs);
+            assert_eq!(resumed, expected);
+        }
+    }
+
+    #[test]
+    fn test_read_rejects_corrupt() {
+        let lattice = Lattice2d::builder()
+            .dims([5, 9])
+            .init_type(InitType::Stripes { width: 2 })
+            .seed(3)
+            .build()
+            .unwrap();
+        let checkpoint = Checkpoint {
+            quantity: Quantity::Magnetization,
+            params: params(),
+            lattice,
+            samples: vec![vec![0.5, -0.25], vec![1.0]],
+            seeds: vec![7, 8, 9],
+        };
+        let mut bytes = vec![];
+        checkpoint.write_to(&mut bytes).unwrap();
+        let read = |bytes: &[u8]| Checkpoint::read_from(&mut &bytes[..]);
+        assert!(read(&bytes).is_ok());
+
+        // cut off anywhere
+        for len in 0..bytes.len() {
+            assert!(matches!(read(&bytes[..len]), Err(IsingError::Format(_))), "len {}", len);
+        }
+
+        // dims come after the magic, version, quantity and params
+        let with_dims = |dims: [u64; 2]| {
+            let mut bytes = bytes.clone();
+            bytes[45..53].copy_from_slice(&dims[0].to_le_bytes());
+            bytes[53..61].copy_from_slice(&dims[1].to_le_bytes());
+            bytes
+        };
+        for dims in [[0, 9], [5, 0], [1 << 20, 1 << 20], [u64::MAX, 2]] {
+            assert!(matches!(read(&with_dims(dims)), Err(IsingError::Format(_))), "dims {:?}", dims);
+        }
+
+        // j, h and beta follow the update rule, spin type and stripes
+        for (offset, x) in [(72, f64::NAN), (80, f64::INFINITY), (88, -1.0)] {
+            let mut bad = bytes.clone();
+            bad[offset..offset + 8].copy_from_slice(&x.to_le_bytes());
+            assert!(matches!(read(&bad), Err(IsingError::InvalidParameter { .. })), "{} at {}", x, offset);
+        }
+
+        // a huge number of runs, n_started sits before the 48 bytes of
+        // samples and the 24 bytes of seeds
+        let mut huge = bytes.clone();
+        huge[13..21].copy_from_slice(&u64::MAX.to_le_bytes());
+        let n_started = huge.len() - 72;
+        huge[n_started..n_started + 8].copy_from_slice(&(u64::MAX >> 1).to_le_bytes());
+        assert!(matches!(read(&huge), Err(IsingError::Format(_))));
+    }
+
+    #[test]
+    fn test_load_rejects_garbage() {
+        let config = temp_config("garbage", 1);
+        fs::write(&config.path, b"definitely not a checkpoint").unwrap();
+        let result = Checkpoint::load(&config);
+        fs::remove_file(&config.path).unwrap();
+        assert!(matches!(result, Err(IsingError::Format(_))));
+    }
+}
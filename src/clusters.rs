@@ -0,0 +1,361 @@
+//! Cluster labelling on the lattice by the Hoshen–Kopelman algorithm.
+//! Geometric clusters are the connected domains of like spins,
+//! Fortuin–Kasteleyn (FK) clusters keep each satisfied bond with
+//! probability 1 - exp(-2β|J|). At Tc the FK clusters percolate, the
+//! geometric ones (in 2d) percolate as well but have a different fractal
+//! dimension.
+
+use crate::lattice2d::Lattice2d;
+use ndarray::prelude::*;
+use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Which bonds join two neighbouring sites into the same cluster
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ClusterKind {
+    Geometric,        // neighbours with the same spin
+    FortuinKasteleyn, // satisfied bonds, kept with probability 1 - exp(-2β|J|)
+}
+
+/// The clusters of a configuration, labelled 0..n_clusters in the order
+/// their first site comes in a row by row scan
+#[derive(Clone, Debug, PartialEq)]
+pub struct Clusters {
+    pub labels: Array2<usize>, // the cluster of each site, VACANT for vacancies
+    pub sizes: Vec<usize>,     // the number of sites in each cluster
+    pub wraps: Vec<Wrapping>,  // the directions each cluster wraps around the torus
+}
+
+/// The directions in which a cluster wraps around the periodic lattice,
+/// i.e. contains a loop that winds around the torus. On a torus this is
+/// what spanning the lattice means.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Wrapping {
+    pub horizontal: bool, // around the columns, along a row
+    pub vertical: bool,   // around the rows, along a column
+}
+
+impl Wrapping {
+    pub fn either(&self) -> bool {
+        self.horizontal || self.vertical
+    }
+
+    pub fn both(&self) -> bool {
+        self.horizontal && self.vertical
+    }
+}
+
+impl Clusters {
+    /// The label of vacant sites, which belong to no cluster
+    pub const VACANT: usize = usize::MAX;
+
+    /// Labels the clusters of `lattice` with periodic boundaries. The
+    /// rng decides which FK bonds are kept, it is not used for geometric
+    /// clusters.
+    pub fn label<R: Rng>(lattice: &Lattice2d, kind: ClusterKind, rng: &mut R) -> Self {
+        let nodes = &lattice.nodes;
+        let (rows, cols) = nodes.dim();
+        let p_bond = 1.0 - (-2.0 * lattice.beta * lattice.j.abs()).exp();
+        let mut bonded = |a: (usize, usize), b: (usize, usize)| {
+            let (sa, sb) = (nodes[a], nodes[b]);
+            if sa == 0 || sb == 0 {
+                return false;
+            }
+            match kind {
+                ClusterKind::Geometric => sa == sb,
+                ClusterKind::FortuinKasteleyn => {
+                    lattice.j * (sa * sb) as f64 > 0.0 && rng.gen::<f64>() < p_bond
+                }
+            }
+        };
+
+        // one pass assigning provisional labels from the site above and
+        // the site to the left, merging them where both are bonded
+        let mut labels = Array2::from_elem((rows, cols), Clusters::VACANT);
+        let mut forest = Forest::default();
+        for i in 0..rows {
+            for j in 0..cols {
+                if nodes[[i, j]] == 0 {
+                    continue;
+                }
+                let up = (i > 0 && bonded((i - 1, j), (i, j))).then(|| labels[[i - 1, j]]);
+                let left = (j > 0 && bonded((i, j - 1), (i, j))).then(|| labels[[i, j - 1]]);
+                labels[[i, j]] = match (up, left) {
+                    (None, None) => forest.push(),
+                    (Some(a), None) | (None, Some(a)) => a,
+                    (Some(a), Some(b)) => forest.union(a, b, [0, 0]),
+                };
+            }
+        }
+        // the bonds around the torus, the sites on the other side are one
+        // period further in the unwrapped plane
+        for i in 0..rows {
+            if bonded((i, cols - 1), (i, 0)) {
+                forest.union(labels[[i, cols - 1]], labels[[i, 0]], [0, cols as i64]);
+            }
+        }
+        for j in 0..cols {
+            if bonded((rows - 1, j), (0, j)) {
+                forest.union(labels[[rows - 1, j]], labels[[0, j]], [rows as i64, 0]);
+            }
+        }
+
+        // relabel the roots of the provisional labels as 0, 1, ...
+        let mut final_label = vec![Clusters::VACANT; forest.parent.len()];
+        let mut sizes = vec![];
+        let mut wraps = vec![];
+        for label in labels.iter_mut().filter(|label| **label != Clusters::VACANT) {
+            let (root, _) = forest.find(*label);
+            if final_label[root] == Clusters::VACANT {
+                final_label[root] = sizes.len();
+                sizes.push(0);
+                wraps.push(forest.wraps[root]);
+            }
+            *label = final_label[root];
+            sizes[*label] += 1;
+        }
+        Clusters { labels, sizes, wraps }
+    }
+
+    pub fn n_clusters(&self) -> usize {
+        self.sizes.len()
+    }
+
+    /// The size of the largest cluster, 0 if all sites are vacant
+    pub fn largest(&self) -> usize {
+        self.sizes.iter().copied().max().unwrap_or(0)
+    }
+
+    /// The fraction of sites in the largest cluster, the order parameter
+    /// of percolation
+    pub fn largest_fraction(&self) -> f64 {
+        self.largest() as f64 / self.labels.len() as f64
+    }
+
+    /// Whether any cluster wraps horizontally and whether any (possibly
+    /// other) cluster wraps vertically
+    pub fn wrapping(&self) -> Wrapping {
+        self.wraps.iter().fold(Wrapping::default(), |acc, wraps| Wrapping {
+            horizontal: acc.horizontal || wraps.horizontal,
+            vertical: acc.vertical || wraps.vertical,
+        })
+    }
+
+    /// The cluster size distribution n_s, entry s is the number of
+    /// clusters of size s per site (entry 0 is always 0)
+    pub fn size_distribution(&self) -> Vec<f64> {
+        let n_sites = self.labels.len() as f64;
+        let mut n_s = vec![0.0; self.largest() + 1];
+        for &size in self.sizes.iter() {
+            n_s[size] += 1.0 / n_sites;
+        }
+        n_s
+    }
+}
+
+/// Union-find over the provisional labels. Each label has its own frame
+/// in the unwrapped plane, offset from the frame of its parent, so that a
+/// bond closing a loop with a nonzero displacement shows that the cluster
+/// wraps around the torus.
+#[derive(Default)]
+struct Forest {
+    parent: Vec<usize>,
+    offset: Vec<[i64; 2]>, // position in the parent's frame minus position in the own frame
+    wraps: Vec<Wrapping>,  // valid for roots
+}
+
+impl Forest {
+    /// A new provisional label
+    fn push(&mut self) -> usize {
+        self.parent.push(self.parent.len());
+        self.offset.push([0, 0]);
+        self.wraps.push(Wrapping::default());
+        self.parent.len() - 1
+    }
+
+    /// The root of a label and the offset of the label's frame in the
+    /// root's frame, pointing the path straight at the root on the way
+    fn find(&mut self, label: usize) -> (usize, [i64; 2]) {
+        let mut root = label;
+        let mut total = [0, 0];
+        while self.parent[root] != root {
+            total = add(total, self.offset[root]);
+            root = self.parent[root];
+        }
+        let (mut node, mut node_offset) = (label, total);
+        while self.parent[node] != node {
+            let next = self.parent[node];
+            let next_offset = sub(node_offset, self.offset[node]);
+            self.parent[node] = root;
+            self.offset[node] = node_offset;
+            (node, node_offset) = (next, next_offset);
+        }
+        (root, total)
+    }
+
+    /// Merges the clusters of labels a and b, given a bond whose far end
+    /// is at position p in b's frame and p + d in a's. Returns the root.
+    fn union(&mut self, a: usize, b: usize, d: [i64; 2]) -> usize {
+        let (root_a, offset_a) = self.find(a);
+        let (root_b, offset_b) = self.find(b);
+        // position in root a's frame minus position in root b's frame
+        let shift = sub(add(offset_a, d), offset_b);
+        if root_a == root_b {
+            let wraps = &mut self.wraps[root_a];
+            wraps.vertical |= shift[0] != 0;
+            wraps.horizontal |= shift[1] != 0;
+            return root_a;
+        }
+        let (root, child, shift) = if root_a < root_b {
+            (root_a, root_b, shift)
+        } else {
+            (root_b, root_a, sub([0, 0], shift))
+        };
+        self.parent[child] = root;
+        self.offset[child] = shift;
+        let child_wraps = self.wraps[child];
+        let wraps = &mut self.wraps[root];
+        wraps.vertical |= child_wraps.vertical;
+        wraps.horizontal |= child_wraps.horizontal;
+        root
+    }
+}
+
+fn add(a: [i64; 2], b: [i64; 2]) -> [i64; 2] {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+fn sub(a: [i64; 2], b: [i64; 2]) -> [i64; 2] {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lattice2d::InitType;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn lattice(spins: Array2<i32>) -> Lattice2d {
+        Lattice2d::builder().dims([spins.nrows(), spins.ncols()]).init_array(spins).build().unwrap()
+    }
+
+    #[test]
+    fn test_geometric_clusters() {
+        // the down spins of columns 1 and 3 are joined by row 2, after
+        // they got different provisional labels, and the up spins of
+        // column 2 continue in row 3 around the torus
+        let lattice = lattice(array![
+            [1, -1, 1, -1, -1],
+            [1, -1, 1, 1, -1],
+            [1, -1, -1, -1, -1],
+            [-1, 1, 1, -1, 1],
+        ]);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let clusters = Clusters::label(&lattice, ClusterKind::Geometric, &mut rng);
+        assert_eq!(
+            clusters.labels,
+            array![[0, 1, 2, 1, 1], [0, 1, 2, 2, 1], [0, 1, 1, 1, 1], [3, 2, 2, 1, 4]]
+        );
+        assert_eq!(clusters.sizes, vec![3, 10, 5, 1, 1]);
+        // the down spins of columns 3 and 4 loop around the rows, the up
+        // spins of column 2 are cut by row 2
+        assert_eq!(clusters.wraps[1], Wrapping { horizontal: false, vertical: true });
+        assert!(clusters.wraps.iter().enumerate().all(|(label, wraps)| label == 1 || !wraps.either()));
+        assert_eq!(clusters.n_clusters(), 5);
+        assert_eq!(clusters.largest(), 10);
+        assert!((clusters.largest_fraction() - 0.5).abs() < 1e-12);
+        let n_s = clusters.size_distribution();
+        assert_eq!(n_s.len(), 11);
+        assert!((n_s[1] - 0.1).abs() < 1e-12 && (n_s[10] - 0.05).abs() < 1e-12);
+        assert_eq!(n_s[4], 0.0);
+        // ∑ s n_s is the fraction of occupied sites
+        let occupied: f64 = n_s.iter().enumerate().map(|(s, n)| s as f64 * n).sum();
+        assert!((occupied - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_wrapping() {
+        // a cross of up spins wraps both ways, the down spins fill the
+        // rest of the torus as a single block without wrapping
+        let mut spins = Array2::from_elem((4, 5), -1);
+        spins.row_mut(1).fill(1);
+        spins.column_mut(2).fill(1);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let clusters = Clusters::label(&lattice(spins), ClusterKind::Geometric, &mut rng);
+        assert_eq!(clusters.sizes, vec![12, 8]);
+        assert_eq!(clusters.wraps, vec![Wrapping::default(), Wrapping { horizontal: true, vertical: true }]);
+        assert!(clusters.wrapping().both());
+
+        // as does a uniform lattice
+        let clusters = Clusters::label(&lattice(Array2::from_elem((3, 3), 1)), ClusterKind::Geometric, &mut rng);
+        assert!(clusters.wrapping().both());
+
+        // horizontal stripes only wrap horizontally
+        let spins = array![[1, 1, 1, 1], [1, 1, 1, 1], [-1, -1, -1, -1], [-1, -1, -1, -1]];
+        let clusters = Clusters::label(&lattice(spins), ClusterKind::Geometric, &mut rng);
+        assert_eq!(clusters.n_clusters(), 2);
+        assert_eq!(clusters.wrapping(), Wrapping { horizontal: true, vertical: false });
+
+        // a staircase winds around both ways at once
+        let spins = array![[1, 1, -1, -1], [-1, 1, 1, -1], [-1, -1, 1, 1], [1, -1, -1, 1]];
+        let clusters = Clusters::label(&lattice(spins), ClusterKind::Geometric, &mut rng);
+        assert_eq!(clusters.n_clusters(), 2);
+        assert!(clusters.wraps.iter().all(|wraps| wraps.both()));
+
+        // a closed ring of up spins doesn't wrap, unlike the rest
+        let mut spins = Array2::from_elem((5, 5), -1);
+        spins.slice_mut(s![1..4, 1..4]).fill(1);
+        spins[[2, 2]] = -1;
+        let clusters = Clusters::label(&lattice(spins), ClusterKind::Geometric, &mut rng);
+        assert_eq!(clusters.sizes, vec![16, 8, 1]);
+        assert_eq!(clusters.wraps[1], Wrapping::default());
+        assert_eq!(clusters.wrapping(), Wrapping { horizontal: true, vertical: true });
+    }
+
+    #[test]
+    fn test_vacancies() {
+        let mut vacancies = Array2::from_elem((3, 3), false);
+        vacancies[[1, 1]] = true;
+        let lattice = Lattice2d::builder()
+            .dims([3, 3])
+            .init_type(InitType::AllUp)
+            .vacancies(vacancies)
+            .build()
+            .unwrap();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let clusters = Clusters::label(&lattice, ClusterKind::Geometric, &mut rng);
+        assert_eq!(clusters.labels[[1, 1]], Clusters::VACANT);
+        assert_eq!(clusters.sizes, vec![8]);
+        assert!((clusters.largest_fraction() - 8.0 / 9.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_fk_clusters() {
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        let mut lattice = Lattice2d::builder().dims([16, 16]).init_type(InitType::AllUp).build().unwrap();
+        // at β = 0 no bonds are kept, every site is its own cluster
+        lattice.beta = 0.0;
+        let clusters = Clusters::label(&lattice, ClusterKind::FortuinKasteleyn, &mut rng);
+        assert_eq!(clusters.n_clusters(), 256);
+        assert_eq!(clusters.size_distribution(), vec![0.0, 1.0]);
+        // cold, almost every bond is kept
+        lattice.beta = 10.0;
+        let clusters = Clusters::label(&lattice, ClusterKind::FortuinKasteleyn, &mut rng);
+        assert_eq!(clusters.sizes, vec![256]);
+        // FK clusters are subsets of the geometric ones
+        lattice.beta = 0.44;
+        lattice.reset_spins();
+        let fk = Clusters::label(&lattice, ClusterKind::FortuinKasteleyn, &mut rng);
+        let geometric = Clusters::label(&lattice, ClusterKind::Geometric, &mut rng);
+        assert!(fk.n_clusters() >= geometric.n_clusters());
+        for (a, b) in [((0, 0), (0, 1)), ((3, 4), (4, 4)), ((15, 2), (0, 2))] {
+            if fk.labels[a] == fk.labels[b] {
+                assert_eq!(geometric.labels[a], geometric.labels[b]);
+            }
+        }
+    }
+}
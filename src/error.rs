@@ -0,0 +1,80 @@
+//! The error type shared across the crate. Invalid parameters and
+//! unimplemented options are reported as an `IsingError` rather than
+//! a panic, so that long batch jobs can handle them gracefully.
+
+use std::fmt;
+use std::io;
+
+/// Everything that can go wrong when setting up or running a simulation
+#[derive(Debug)]
+pub enum IsingError {
+    /// The dimensions of the graph are empty or too large
+    InvalidDims(Vec<usize>),
+    /// A numerical parameter is out of its allowed range
+    InvalidParameter { name: &'static str, reason: String },
+    /// The initial condition doesn't fit the graph
+    InvalidInitType(String),
+    /// The requested option exists but is not implemented yet
+    Unimplemented(&'static str),
+    /// Reading or writing a file failed
+    Io(io::Error),
+    /// A file was read but its contents are malformed
+    Format(String),
+    /// A worker thread of a parallel sampler panicked, with its message
+    WorkerPanicked(String),
+}
+
+impl fmt::Display for IsingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IsingError::InvalidDims(dims) => write!(f, "invalid dimensions {:?}", dims),
+            IsingError::InvalidParameter { name, reason } => {
+                write!(f, "invalid parameter `{}`: {}", name, reason)
+            }
+            IsingError::InvalidInitType(msg) => write!(f, "invalid initial condition: {}", msg),
+            IsingError::Unimplemented(what) => write!(f, "{} is not implemented yet", what),
+            IsingError::Io(err) => write!(f, "io error: {}", err),
+            IsingError::Format(msg) => write!(f, "malformed file: {}", msg),
+            IsingError::WorkerPanicked(msg) => write!(f, "a worker thread panicked: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for IsingError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IsingError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for IsingError {
+    fn from(err: io::Error) -> Self {
+        IsingError::Io(err)
+    }
+}
+
+/// Checks that a parameter is a finite number
+pub fn check_finite(name: &'static str, value: f64) -> Result<(), IsingError> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(IsingError::InvalidParameter {
+            name,
+            reason: format!("must be finite, got {}", value),
+        })
+    }
+}
+
+/// Checks that a parameter is a probability, i.e. in [0, 1]
+pub(crate) fn check_probability(name: &'static str, value: f64) -> Result<(), IsingError> {
+    if (0.0..=1.0).contains(&value) {
+        Ok(())
+    } else {
+        Err(IsingError::InvalidParameter {
+            name,
+            reason: format!("must be in [0, 1], got {}", value),
+        })
+    }
+}
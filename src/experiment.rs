@@ -0,0 +1,635 @@
+//! Declarative experiment files (needs the `toml` cargo feature). A whole
+//! campaign (the model, the geometry, the temperature and field grids,
+//! the disorder realizations, the sampler, the observables and where the
+//! results go) is described in one TOML file, so that a published result
+//! can be reproduced from a single checked-in file.
+//!
+//! ```toml
+//! name = "nn correlation against temperature"
+//! seed = 42                 # seeds the dynamics, random if left out
+//! temperatures = { start = 0.5, stop = 6.0, step = 0.2 }
+//! fields = [0.0]            # a list works for any grid, the default is [0.0]
+//! observables = ["NeighborCorrelation", "Magnetization"]
+//!
+//! [model]
+//! j = 1.0
+//! update_rule = "Metropolis"
+//!
+//! [geometry]
+//! type = "lattice2d"
+//! dims = [25, 25]
+//! init = "Random"
+//! boundary = "Periodic"
+//!
+//! [disorder]
+//! vacancy_fraction = 0.05   # random site dilution
+//! seeds = [1, 2, 3]         # one realization per seed
+//!
+//! [sampler]
+//! n_runs = 25
+//! flips_to_skip = 300_000
+//! samples_per_run = 10
+//! flips_to_skip_between_samples = 30_000
+//! parallel = true
+//! threads = 8              # worker threads, one per CPU by default
+//!
+//! [output]
+//! summary = "data/summary.csv"  # mean, std and sem per point and observable
+//! samples = "data/samples.npy"  # every sample
+//! ```
+//!
+//! Relative paths are relative to the directory of the experiment file.
+//!
+//! ```no_run
+//! use ising_lib::experiment::Experiment;
+//!
+//! let experiment = Experiment::load("experiments/nn_corr.toml").unwrap();
+//! let results = experiment.run().unwrap();
+//! ```
+
+use crate::error::{check_finite, check_probability, IsingError};
+use crate::graph::{EdgeType, Graph};
+use crate::image::Image;
+use crate::lattice2d::{Boundary, InitType, Lattice2d, UpdateRule};
+use crate::monte_carlo_measurement::{MonteCarlo, MonteCarloParams};
+use crate::observable::{Observable, Quantity};
+use crate::output::{Cell, Format, TableWriter};
+use crate::pool;
+use crate::sample_set::Stats;
+use ndarray::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// A campaign of Monte Carlo sampling over a grid of temperatures,
+/// fields and disorder realizations
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Experiment {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub seed: Option<u64>, // the rng of grid point i is seeded with seed + i
+    pub temperatures: Grid,
+    #[serde(default = "Grid::zero")]
+    pub fields: Grid,
+    pub observables: Vec<Quantity>,
+    #[serde(default)]
+    pub model: Model,
+    pub geometry: Geometry,
+    #[serde(default)]
+    pub disorder: Disorder,
+    pub sampler: Sampler,
+    #[serde(default)]
+    pub output: Output,
+    #[serde(skip)]
+    pub base_dir: PathBuf, // relative paths are resolved against this
+}
+
+/// Values of a scanned parameter, a list or an inclusive range
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Grid {
+    List(Vec<f64>),
+    Range { start: f64, stop: f64, step: f64 },
+}
+
+/// The Hamiltonian, the temperature and field come from the grids
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Model {
+    #[serde(default = "Model::default_j")]
+    pub j: f64,
+    #[serde(default = "Model::default_update_rule")]
+    pub update_rule: UpdateRule,
+}
+
+/// What the spins live on
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Geometry {
+    Lattice2d {
+        dims: [usize; 2],
+        #[serde(default = "Geometry::default_init")]
+        init: InitType,
+        #[serde(default = "Geometry::default_boundary")]
+        boundary: Boundary,
+        #[serde(default)]
+        vacancies: Option<PathBuf>, // image mask, dark is vacant
+        #[serde(default)]
+        pinned: Option<PathBuf>, // image mask, dark is pinned
+        #[serde(default = "Geometry::default_threshold")]
+        threshold: u8,
+    },
+    /// A random graph, see `Experiment::graph`. The Monte Carlo samplers
+    /// don't work on a `Graph` yet, so `run` rejects it.
+    Graph {
+        n_sites: u32,
+        edge_prob: f64,
+    },
+}
+
+/// Quenched disorder, each seed is one realization
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Disorder {
+    #[serde(default)]
+    pub vacancy_fraction: f64, // each site is vacant with this probability
+    #[serde(default)]
+    pub seeds: Vec<u64>, // no seeds means a single realization without dilution
+}
+
+/// The `MonteCarloParams`, whether runs go in parallel and on how many
+/// worker threads, by default `pool::default_n_threads()`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sampler {
+    pub n_runs: usize,
+    pub flips_to_skip: usize,
+    pub samples_per_run: usize,
+    pub flips_to_skip_between_samples: usize,
+    #[serde(default)]
+    pub parallel: bool,
+    #[serde(default)]
+    pub threads: Option<usize>,
+}
+
+/// Where the results are written, the format by the file extension
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Output {
+    #[serde(default)]
+    pub summary: Option<PathBuf>,
+    #[serde(default)]
+    pub samples: Option<PathBuf>,
+}
+
+/// The samples of one observable at one point of the grid
+#[derive(Clone, Debug)]
+pub struct PointResult {
+    pub temperature: f64,
+    pub field: f64,
+    pub disorder_seed: Option<u64>,
+    pub quantity: Quantity,
+    pub samples: Vec<Vec<f64>>, // one vec per run
+}
+
+impl Grid {
+    fn zero() -> Self {
+        Grid::List(vec![0.0])
+    }
+
+    /// The values of the grid, a range includes `stop` if it is hit to
+    /// within rounding
+    pub fn values(&self) -> Result<Vec<f64>, IsingError> {
+        let values = match *self {
+            Grid::List(ref values) => values.clone(),
+            Grid::Range { start, stop, step } => {
+                check_finite("start", start)?;
+                check_finite("stop", stop)?;
+                if !(step > 0.0 && step.is_finite()) || stop < start {
+                    return Err(IsingError::InvalidParameter {
+                        name: "step",
+                        reason: format!("no range from {} to {} in steps of {}", start, stop, step),
+                    });
+                }
+                let n = ((stop - start) / step + 1e-9).floor() as usize + 1;
+                (0..n).map(|i| start + i as f64 * step).collect()
+            }
+        };
+        if values.is_empty() {
+            return Err(IsingError::InvalidParameter {
+                name: "grid",
+                reason: "has no values".to_owned(),
+            });
+        }
+        Ok(values)
+    }
+}
+
+impl Default for Model {
+    fn default() -> Self {
+        Model {
+            j: Model::default_j(),
+            update_rule: Model::default_update_rule(),
+        }
+    }
+}
+
+impl Model {
+    fn default_j() -> f64 {
+        1.0
+    }
+
+    fn default_update_rule() -> UpdateRule {
+        UpdateRule::Metropolis
+    }
+}
+
+impl Geometry {
+    fn default_init() -> InitType {
+        InitType::Random
+    }
+
+    fn default_boundary() -> Boundary {
+        Boundary::Periodic
+    }
+
+    fn default_threshold() -> u8 {
+        128
+    }
+}
+
+impl Sampler {
+    pub fn params(&self) -> MonteCarloParams {
+        MonteCarloParams {
+            n_runs: self.n_runs,
+            flips_to_skip: self.flips_to_skip,
+            samples_per_run: self.samples_per_run,
+            flips_to_skip_between_samples: self.flips_to_skip_between_samples,
+        }
+    }
+}
+
+impl Experiment {
+    /// Parses and validates an experiment, relative paths are resolved
+    /// against the current directory
+    pub fn from_toml(s: &str) -> Result<Self, IsingError> {
+        let experiment: Experiment = toml::from_str(s).map_err(|err| IsingError::Format(err.to_string()))?;
+        experiment.validate()?;
+        Ok(experiment)
+    }
+
+    /// Reads, parses and validates an experiment file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, IsingError> {
+        let path = path.as_ref();
+        let mut experiment = Experiment::from_toml(&fs::read_to_string(path)?)?;
+        experiment.base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(experiment)
+    }
+
+    /// The experiment as TOML
+    pub fn to_toml(&self) -> Result<String, IsingError> {
+        toml::to_string(self).map_err(|err| IsingError::Format(err.to_string()))
+    }
+
+    /// Checks everything that can be checked without touching the files
+    /// it refers to
+    pub fn validate(&self) -> Result<(), IsingError> {
+        for temperature in self.temperatures.values()? {
+            if !(temperature.is_finite() && temperature > 0.0) {
+                return Err(IsingError::InvalidParameter {
+                    name: "temperatures",
+                    reason: format!("{} is not a positive temperature", temperature),
+                });
+            }
+        }
+        for field in self.fields.values()? {
+            check_finite("fields", field)?;
+        }
+        check_finite("j", self.model.j)?;
+        check_probability("vacancy_fraction", self.disorder.vacancy_fraction)?;
+        if self.observables.is_empty() {
+            return Err(IsingError::InvalidParameter {
+                name: "observables",
+                reason: "nothing to measure".to_owned(),
+            });
+        }
+        if self.sampler.n_runs == 0 || self.sampler.samples_per_run == 0 {
+            return Err(IsingError::InvalidParameter {
+                name: "sampler",
+                reason: "n_runs and samples_per_run must be positive".to_owned(),
+            });
+        }
+        if self.sampler.threads == Some(0) {
+            return Err(IsingError::InvalidParameter {
+                name: "threads",
+                reason: "must be at least 1".to_owned(),
+            });
+        }
+        match &self.geometry {
+            Geometry::Lattice2d { dims, init, .. } => {
+                if dims.contains(&0) {
+                    return Err(IsingError::InvalidDims(dims.to_vec()));
+                }
+                init.validate(dims)
+            }
+            Geometry::Graph { n_sites, edge_prob } => {
+                if *n_sites == 0 {
+                    return Err(IsingError::InvalidDims(vec![0]));
+                }
+                check_probability("edge_prob", *edge_prob)
+            }
+        }
+    }
+
+    /// Monte Carlo parameters of every grid point
+    pub fn params(&self) -> MonteCarloParams {
+        self.sampler.params()
+    }
+
+    /// The disorder realizations, `None` for the clean system
+    fn disorder_seeds(&self) -> Vec<Option<u64>> {
+        if self.disorder.seeds.is_empty() {
+            vec![None]
+        } else {
+            self.disorder.seeds.iter().copied().map(Some).collect()
+        }
+    }
+
+    fn resolve(&self, path: &Path) -> PathBuf {
+        self.base_dir.join(path)
+    }
+
+    /// The lattice at one temperature, field and disorder realization
+    pub fn lattice(&self, temperature: f64, field: f64, disorder_seed: Option<u64>) -> Result<Lattice2d, IsingError> {
+        let (dims, init, boundary, vacancies, pinned, threshold) = match &self.geometry {
+            Geometry::Lattice2d {
+                dims,
+                init,
+                boundary,
+                vacancies,
+                pinned,
+                threshold,
+            } => (*dims, init, *boundary, vacancies, pinned, *threshold),
+            Geometry::Graph { .. } => {
+                return Err(IsingError::InvalidParameter {
+                    name: "geometry",
+                    reason: "is a graph, not a lattice".to_owned(),
+                })
+            }
+        };
+        let mut builder = Lattice2d::builder()
+            .dims(dims)
+            .update_rule(self.model.update_rule)
+            .init_type(init.clone())
+            .j(self.model.j)
+            .h(field)
+            .beta(1.0 / temperature);
+        let mut vacancy_mask = match vacancies {
+            Some(path) => Some(Image::load(self.resolve(path))?.to_mask(threshold)),
+            None => None,
+        };
+        if let Some(seed) = disorder_seed {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let fraction = self.disorder.vacancy_fraction;
+            let diluted = Array2::from_shape_simple_fn((dims[0], dims[1]), || rng.gen::<f64>() < fraction);
+            vacancy_mask = Some(match vacancy_mask {
+                Some(mask) if mask.dim() == diluted.dim() => &mask | &diluted,
+                Some(mask) => mask, // the dims don't match, reported by build()
+                None => diluted,
+            });
+        }
+        if let Some(mask) = vacancy_mask {
+            builder = builder.vacancies(mask);
+        }
+        if let Some(path) = pinned {
+            builder = builder.pinned(Image::load(self.resolve(path))?.to_mask(threshold));
+        }
+        let mut lattice = builder.build()?;
+        if boundary != Boundary::Periodic {
+            lattice.set_boundary(boundary);
+        }
+        Ok(lattice)
+    }
+
+    /// The graph at one temperature and field, its edges are random
+    pub fn graph(&self, temperature: f64, field: f64) -> Result<Graph, IsingError> {
+        match self.geometry {
+            Geometry::Graph { n_sites, edge_prob } => Graph::builder()
+                .n_sites(n_sites)
+                .edge_type(EdgeType::BinaryRandom { prob: edge_prob })
+                .j(self.model.j)
+                .h(field)
+                .beta(1.0 / temperature)
+                .build(),
+            Geometry::Lattice2d { .. } => Err(IsingError::InvalidParameter {
+                name: "geometry",
+                reason: "is a lattice, not a graph".to_owned(),
+            }),
+        }
+    }
+
+    /// Samples every observable at every point of the grid, then writes
+    /// the configured outputs. Points are ordered by temperature, then
+    /// field, then disorder seed.
+    pub fn run(&self) -> Result<Vec<PointResult>, IsingError> {
+        self.validate()?;
+        if let Geometry::Graph { .. } = self.geometry {
+            return Err(IsingError::Unimplemented("Monte Carlo sampling on a Graph"));
+        }
+        let params = self.params();
+        let mut results = Vec::new();
+        let mut point = 0;
+        for temperature in self.temperatures.values()? {
+            for field in self.fields.values()? {
+                for disorder_seed in self.disorder_seeds() {
+                    let mut lattice = self.lattice(temperature, field, disorder_seed)?;
+                    if let Some(seed) = self.seed {
+                        lattice.seed(seed.wrapping_add(point));
+                    }
+                    point += 1;
+                    // all observables are measured on the same samples
+                    let observables: Vec<&dyn Observable> =
+                        self.observables.iter().map(|quantity| quantity as &dyn Observable).collect();
+                    let series = if self.sampler.parallel {
+                        let n_threads = self.sampler.threads.unwrap_or_else(pool::default_n_threads);
+                        lattice.sample_observables_parallel(&observables, &params, n_threads)?
+                    } else {
+                        lattice.sample_observables(&observables, &params)
+                    };
+                    for (&quantity, series) in self.observables.iter().zip(series) {
+                        results.push(PointResult {
+                            temperature,
+                            field,
+                            disorder_seed,
+                            quantity,
+                            samples: series.samples,
+                        });
+                    }
+                }
+            }
+        }
+        if let Some(path) = &self.output.summary {
+            let path = self.resolve(path);
+            let mut table = create_table(&path, &SUMMARY_COLUMNS, results.len())?;
+            self.write_summary(&results, &mut table)?;
+            table.finish()?.flush()?;
+        }
+        if let Some(path) = &self.output.samples {
+            let path = self.resolve(path);
+            let n_rows = results.len() * params.n_runs * params.samples_per_run;
+            let mut table = create_table(&path, &SAMPLES_COLUMNS, n_rows)?;
+            self.write_samples(&results, &mut table)?;
+            table.finish()?.flush()?;
+        }
+        Ok(results)
+    }
+
+    /// One row per point and observable with the mean, standard deviation
+    /// and standard error (over run means), see `SUMMARY_COLUMNS`.
+    /// Observables are named in CSV and JSON Lines, in NPY they are
+    /// numbered in the order of the experiment file.
+    pub fn write_summary<W: Write>(&self, results: &[PointResult], table: &mut TableWriter<W>) -> Result<(), IsingError> {
+        for result in results {
+            let (pooled, between_runs) = (Stats::pooled(&result.samples), Stats::between_runs(&result.samples));
+            let mut row = self.point_columns(result);
+            row.extend([pooled.mean, pooled.std, between_runs.sem].map(Cell::Number));
+            table.write_cells(&row)?;
+        }
+        Ok(())
+    }
+
+    /// One row per sample, see `SAMPLES_COLUMNS`
+    pub fn write_samples<W: Write>(&self, results: &[PointResult], table: &mut TableWriter<W>) -> Result<(), IsingError> {
+        for result in results {
+            for (run, values) in result.samples.iter().enumerate() {
+                for (sample, &value) in values.iter().enumerate() {
+                    let mut row = self.point_columns(result);
+                    row.extend([run as f64, sample as f64, value].map(Cell::Number));
+                    table.write_cells(&row)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn point_columns<'a>(&self, result: &'a PointResult) -> Vec<Cell<'a>> {
+        let index = self.observables.iter().position(|&q| q == result.quantity).unwrap_or(0);
+        vec![
+            Cell::Number(result.temperature),
+            Cell::Number(1.0 / result.temperature),
+            Cell::Number(result.field),
+            Cell::Number(result.disorder_seed.map_or(f64::NAN, |seed| seed as f64)),
+            Cell::Label(result.quantity.name(), index as f64),
+        ]
+    }
+}
+
+/// Columns of the summary table
+pub const SUMMARY_COLUMNS: [&str; 8] = ["temperature", "beta", "h", "disorder_seed", "observable", "mean", "std", "sem"];
+/// Columns of the samples table
+pub const SAMPLES_COLUMNS: [&str; 8] = ["temperature", "beta", "h", "disorder_seed", "observable", "run", "sample", "value"];
+
+fn create_table(path: &Path, columns: &[&str], n_rows: usize) -> Result<TableWriter<BufWriter<File>>, IsingError> {
+    let format = Format::from_path(path)?;
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    TableWriter::new(BufWriter::new(File::create(path)?), format, columns, n_rows)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SMALL: &str = r#"
+        seed = 7
+        temperatures = [1.5, 3.0]
+        fields = { start = -0.5, stop = 0.5, step = 0.5 }
+        observables = ["Energy", "Magnetization"]
+
+        [geometry]
+        type = "lattice2d"
+        dims = [6, 6]
+        init = { Stripes = { width = 2 } }
+
+        [disorder]
+        vacancy_fraction = 0.25
+        seeds = [1, 2]
+
+        [sampler]
+        n_runs = 2
+        flips_to_skip = 100
+        samples_per_run = 3
+        flips_to_skip_between_samples = 10
+    "#;
+
+    #[test]
+    fn test_parse() {
+        let experiment = Experiment::from_toml(SMALL).unwrap();
+        assert_eq!(experiment.temperatures.values().unwrap(), vec![1.5, 3.0]);
+        assert_eq!(experiment.fields.values().unwrap(), vec![-0.5, 0.0, 0.5]);
+        assert_eq!(experiment.model.update_rule, UpdateRule::Metropolis);
+        assert_eq!(experiment.params().samples_per_run, 3);
+
+        // the same realization for the same seed, dims from the file
+        let a = experiment.lattice(1.5, 0.0, Some(1)).unwrap();
+        let b = experiment.lattice(3.0, 0.5, Some(1)).unwrap();
+        assert_eq!(a.dims, [6, 6]);
+        assert_eq!(a.vacancies(), b.vacancies());
+        assert_eq!(b.h, 0.5);
+        assert!(experiment.graph(1.5, 0.0).is_err());
+
+        let again = Experiment::from_toml(&experiment.to_toml().unwrap()).unwrap();
+        assert_eq!(again.fields, experiment.fields);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let cases = [
+            SMALL.replace("[1.5, 3.0]", "[1.5, -3.0]"),
+            SMALL.replace("step = 0.5", "step = 0.0"),
+            SMALL.replace("\"Energy\", \"Magnetization\"", ""),
+            SMALL.replace("vacancy_fraction = 0.25", "vacancy_fraction = 2.0"),
+            SMALL.replace("dims = [6, 6]", "dims = [6, 0]"),
+            SMALL.replace("n_runs = 2", "n_run = 2"), // unknown field
+            SMALL.replace("\"Energy\"", "\"Entropy\""),
+            SMALL.replace("samples_per_run = 3", "samples_per_run = 3\nparallel = true\nthreads = 0"),
+        ];
+        for case in cases.iter() {
+            assert!(Experiment::from_toml(case).is_err(), "{}", case);
+        }
+    }
+
+    #[test]
+    fn test_run() {
+        let dir = std::env::temp_dir().join("ising_lib_test_experiment");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let toml = format!("{}\n[output]\nsummary = \"out/summary.csv\"\nsamples = \"out/samples.npy\"\n", SMALL);
+        fs::write(dir.join("small.toml"), toml).unwrap();
+
+        let experiment = Experiment::load(dir.join("small.toml")).unwrap();
+        let results = experiment.run().unwrap();
+        // 2 temperatures x 3 fields x 2 disorder seeds x 2 observables
+        assert_eq!(results.len(), 24);
+        assert!(results.iter().all(|r| r.samples.len() == 2 && r.samples[0].len() == 3));
+        let summary = fs::read_to_string(dir.join("out/summary.csv")).unwrap();
+        assert_eq!(summary.lines().count(), 25);
+        assert!(summary.starts_with("temperature,beta,h,disorder_seed,observable,mean,std,sem\n1.5,"));
+        // observables are named
+        let observables: Vec<&str> = summary.lines().skip(1).map(|line| line.split(',').nth(4).unwrap()).collect();
+        assert_eq!(observables[..2], ["energy", "magnetization"]);
+        assert!(dir.join("out/samples.npy").exists());
+
+        // seeded dynamics are reproducible
+        let again = experiment.run().unwrap();
+        assert_eq!(again[5].samples, results[5].samples);
+        // and the same on the worker pool
+        let parallel = SMALL.replace("samples_per_run = 3", "samples_per_run = 3\nparallel = true\nthreads = 2");
+        let parallel = Experiment::from_toml(&parallel).unwrap().run().unwrap();
+        assert_eq!(parallel[5].samples, results[5].samples);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_graph_geometry() {
+        let toml = SMALL
+            .replace("type = \"lattice2d\"", "type = \"graph\"\nn_sites = 10\nedge_prob = 0.3")
+            .replace("dims = [6, 6]\n", "")
+            .replace("init = { Stripes = { width = 2 } }\n", "");
+        let experiment = Experiment::from_toml(&toml).unwrap();
+        assert_eq!(experiment.graph(2.0, 0.0).unwrap().n_sites, 10);
+        assert!(matches!(experiment.run(), Err(IsingError::Unimplemented(_))));
+        assert!(experiment.lattice(2.0, 0.0, None).is_err());
+
+        let bad = toml.replace("edge_prob = 0.3", "edge_prob = 1.5");
+        assert!(matches!(Experiment::from_toml(&bad), Err(IsingError::InvalidParameter { name: "edge_prob", .. })));
+        let empty = toml.replace("n_sites = 10", "n_sites = 0");
+        assert!(matches!(Experiment::from_toml(&empty), Err(IsingError::InvalidDims(_))));
+    }
+}
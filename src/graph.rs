@@ -1,26 +1,33 @@
 //! Graph spin 1/2 Type
 
+use crate::error::{check_finite, check_probability, IsingError};
 use ndarray::prelude::*;
 use rand::prelude::SliceRandom;
 use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 /// update rule for Graph
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum UpdateRule {
     Metropolis,
 }
 
 /// types of spin system
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum SpinType {
     SpinHalf,
 }
 
 /// initial spin condition
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum InitType {
     Random, // uniformly random
     AllUp,
 }
 
 /// the type of edge between nodes
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum EdgeType {
     BinaryRandom { prob: f64 }, // random edges {0 to 1} between nodes i,j with prob p
     UnifRandom,                 // uniformly random numbers between zero and 1
@@ -28,7 +35,8 @@ pub enum EdgeType {
 
 /// A type encapsulating an Ising model on
 /// a graph and basic operations performed on it
-
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "GraphData"))]
 pub struct Graph {
     pub n_sites: u32,       // = nodes.len()
     pub nodes: Array1<i32>, // An array of nodes
@@ -42,15 +50,66 @@ pub struct Graph {
     pub beta: f64, // beta = 1/(kb * T), defaults to 0.43
 }
 
+/// The serialized form of a graph, checked and converted into a `Graph`
+/// on deserialization
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct GraphData {
+    n_sites: u32,
+    nodes: Array1<i32>,
+    edges: Array2<f64>,
+    update_rule: UpdateRule,
+    edge_type: EdgeType,
+    spin_type: SpinType,
+    init_type: InitType,
+    j: f64,
+    h: f64,
+    beta: f64,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<GraphData> for Graph {
+    type Error = IsingError;
+
+    fn try_from(data: GraphData) -> Result<Self, Self::Error> {
+        check_params(data.n_sites, &data.edge_type, data.j, data.h, data.beta)?;
+        let n = data.n_sites as usize;
+        if data.nodes.len() != n || data.edges.dim() != (n, n) {
+            return Err(IsingError::InvalidDims(vec![data.nodes.len(), data.edges.nrows(), data.edges.ncols()]));
+        }
+        if let Some(&s) = data.nodes.iter().find(|&&s| s != -1 && s != 1) {
+            return Err(IsingError::InvalidParameter {
+                name: "nodes",
+                reason: format!("spins must be -1 or 1, got {}", s),
+            });
+        }
+        for &weight in data.edges.iter() {
+            check_finite("edges", weight)?;
+        }
+        Ok(Graph {
+            n_sites: data.n_sites,
+            nodes: data.nodes,
+            edges: data.edges,
+            update_rule: data.update_rule,
+            edge_type: data.edge_type,
+            spin_type: data.spin_type,
+            init_type: data.init_type,
+            j: data.j,
+            h: data.h,
+            beta: data.beta,
+        })
+    }
+}
+
 /// Implement basic methods on Graph type
 impl Graph {
     /// Create a new Graph of given size with random edges
     pub fn new_basic(n_sites: u32, prob: f64) -> Self {
-        assert!(prob >= 0.0 && prob <= 1.0);
+        assert!((0.0..=1.0).contains(&prob));
         Self::new(
             n_sites,
             UpdateRule::Metropolis,
-            EdgeType::BinaryRandom { prob: prob },
+            EdgeType::BinaryRandom { prob },
             SpinType::SpinHalf,
             InitType::Random,
             1.0f64,
@@ -59,6 +118,15 @@ impl Graph {
         )
     }
 
+    /// Start building a Graph with named parameters, see `GraphBuilder`
+    pub fn builder() -> GraphBuilder {
+        GraphBuilder::default()
+    }
+
+    /// Create a new Graph with specific implementation details
+    ///
+    /// Panics if the edge type is not implemented, prefer `Graph::builder`
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         n_sites: u32,
         update_rule: UpdateRule,
@@ -70,42 +138,164 @@ impl Graph {
         beta: f64,
     ) -> Self {
         // TODO: implement init for different spin types
-        let nodes: Array1<i32>;
         let mut rng = rand::thread_rng();
-        match init_type {
+        let nodes: Array1<i32> = match init_type {
             InitType::Random => {
-                nodes = Array::from_iter((0..n_sites).map(|_| *[-1, 1].choose(&mut rng).unwrap()));
-            }
-            InitType::AllUp => {
-                nodes = Array::from_iter((0..n_sites).map(|_| 1));
+                Array::from_iter((0..n_sites).map(|_| *[-1, 1].choose(&mut rng).unwrap()))
             }
-        }
+            InitType::AllUp => Array::from_iter((0..n_sites).map(|_| 1)),
+        };
         // TODO: implement init for different edge types
-        let edges; // : Array2<f64> = Array2<f64>::zeros((n_sites, n_sites));
-        match edge_type {
+        let edges: Array2<f64> = match edge_type {
             EdgeType::BinaryRandom { prob } => {
                 // Probabilistically fill the edge matrix with ones with prob p, and zeros with prob 1-p
-                edges = Array2::from_shape_fn([n_sites as usize , n_sites as usize], |_| -> f64 {if rng.gen::<f64>() < prob {1.0} else {0.0}}); // *[0.0,1.0].choose(&mut rng, prob).unwrap());
+                Array2::from_shape_fn([n_sites as usize , n_sites as usize], |_| -> f64 {if rng.gen::<f64>() < prob {1.0} else {0.0}}) // *[0.0,1.0].choose(&mut rng, prob).unwrap());
             }
             _ => {
                 panic!("Not yet implemented edge type, try EdgeType::BinaryRandom instead")
             }
         };
         Graph {
-            n_sites:n_sites,
-            nodes:nodes,
-            edges:edges,
-            update_rule:update_rule,
-            edge_type:edge_type,
-            spin_type:spin_type,
-            init_type:init_type,
-            j:j,
-            h:h,
-            beta:beta,
+            n_sites,
+            nodes,
+            edges,
+            update_rule,
+            edge_type,
+            spin_type,
+            init_type,
+            j,
+            h,
+            beta,
         }
     }
 }
 
+/// Builds a `Graph` from named parameters, validating them first.
+/// Unset parameters default to the same values as `Graph::new_basic`.
+///
+/// ```
+/// use ising_lib::graph::{EdgeType, Graph};
+///
+/// let graph = Graph::builder()
+///     .n_sites(20)
+///     .edge_type(EdgeType::BinaryRandom { prob: 0.3 })
+///     .beta(0.5)
+///     .build()
+///     .unwrap();
+/// assert_eq!(graph.nodes.len(), 20);
+/// ```
+pub struct GraphBuilder {
+    n_sites: u32,
+    update_rule: UpdateRule,
+    edge_type: EdgeType,
+    spin_type: SpinType,
+    init_type: InitType,
+    j: f64,
+    h: f64,
+    beta: f64,
+}
+
+impl Default for GraphBuilder {
+    fn default() -> Self {
+        GraphBuilder {
+            n_sites: 100,
+            update_rule: UpdateRule::Metropolis,
+            edge_type: EdgeType::BinaryRandom { prob: 0.5 },
+            spin_type: SpinType::SpinHalf,
+            init_type: InitType::Random,
+            j: 1.0,
+            h: 0.0,
+            beta: 0.43,
+        }
+    }
+}
+
+impl GraphBuilder {
+    /// Number of nodes, default 100
+    pub fn n_sites(mut self, n_sites: u32) -> Self {
+        self.n_sites = n_sites;
+        self
+    }
+
+    /// Update rule, default `UpdateRule::Metropolis`
+    pub fn update_rule(mut self, update_rule: UpdateRule) -> Self {
+        self.update_rule = update_rule;
+        self
+    }
+
+    /// Type of edges, default `BinaryRandom { prob: 0.5 }`
+    pub fn edge_type(mut self, edge_type: EdgeType) -> Self {
+        self.edge_type = edge_type;
+        self
+    }
+
+    /// Spin type, default `SpinType::SpinHalf`
+    pub fn spin_type(mut self, spin_type: SpinType) -> Self {
+        self.spin_type = spin_type;
+        self
+    }
+
+    /// Initial condition, default `InitType::Random`
+    pub fn init_type(mut self, init_type: InitType) -> Self {
+        self.init_type = init_type;
+        self
+    }
+
+    /// Interaction constant, default 1.0
+    pub fn j(mut self, j: f64) -> Self {
+        self.j = j;
+        self
+    }
+
+    /// External uniform magnetic field, default 0.0
+    pub fn h(mut self, h: f64) -> Self {
+        self.h = h;
+        self
+    }
+
+    /// beta = 1/(kb * T), default 0.43
+    pub fn beta(mut self, beta: f64) -> Self {
+        self.beta = beta;
+        self
+    }
+
+    /// Validates the parameters and creates the Graph
+    pub fn build(self) -> Result<Graph, IsingError> {
+        check_params(self.n_sites, &self.edge_type, self.j, self.h, self.beta)?;
+        Ok(Graph::new(
+            self.n_sites,
+            self.update_rule,
+            self.edge_type,
+            self.spin_type,
+            self.init_type,
+            self.j,
+            self.h,
+            self.beta,
+        ))
+    }
+}
+
+/// Checks the parameters shared by `GraphBuilder` and deserialization
+fn check_params(n_sites: u32, edge_type: &EdgeType, j: f64, h: f64, beta: f64) -> Result<(), IsingError> {
+    if n_sites == 0 {
+        return Err(IsingError::InvalidDims(vec![0]));
+    }
+    match *edge_type {
+        EdgeType::BinaryRandom { prob } => check_probability("prob", prob)?,
+        EdgeType::UnifRandom => return Err(IsingError::Unimplemented("EdgeType::UnifRandom")),
+    }
+    check_finite("j", j)?;
+    check_finite("h", h)?;
+    check_finite("beta", beta)?;
+    if beta < 0.0 {
+        return Err(IsingError::InvalidParameter {
+            name: "beta",
+            reason: format!("must be non-negative, got {}", beta),
+        });
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -123,4 +313,37 @@ mod tests {
             0.4f64,
         );
     }
+
+    #[test]
+    fn test_graph_builder() {
+        let graph = Graph::builder().n_sites(12).beta(0.3).build().unwrap();
+        assert_eq!(graph.nodes.len(), 12);
+        assert_eq!(graph.edges.dim(), (12, 12));
+
+        let err = Graph::builder().edge_type(EdgeType::UnifRandom).build();
+        assert!(matches!(err, Err(IsingError::Unimplemented(_))));
+        let err = Graph::builder().edge_type(EdgeType::BinaryRandom { prob: 1.2 }).build();
+        assert!(matches!(err, Err(IsingError::InvalidParameter { name: "prob", .. })));
+        let err = Graph::builder().beta(-1.0).build();
+        assert!(matches!(err, Err(IsingError::InvalidParameter { name: "beta", .. })));
+        assert!(Graph::builder().n_sites(0).build().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_roundtrip() {
+        let graph = Graph::builder().n_sites(6).build().unwrap();
+        let json = serde_json::to_string(&graph).unwrap();
+        let copy: Graph = serde_json::from_str(&json).unwrap();
+        assert_eq!(copy.nodes, graph.nodes);
+        assert_eq!(copy.edges, graph.edges);
+
+        // inconsistent or invalid graphs are rejected
+        let bad = json.replacen("\"n_sites\":6", "\"n_sites\":7", 1);
+        assert!(serde_json::from_str::<Graph>(&bad).is_err());
+        let bad = json.replacen("\"data\":[1", "\"data\":[7", 1).replacen("\"data\":[-1", "\"data\":[7", 1);
+        assert!(serde_json::from_str::<Graph>(&bad).is_err());
+        let bad = json.replacen("\"beta\":0.43", "\"beta\":-1.0", 1);
+        assert!(serde_json::from_str::<Graph>(&bad).is_err());
+    }
 }
@@ -0,0 +1,614 @@
+//! Export of lattice configurations as images. Spins, and scalar fields
+//! such as Potts states or continuous spins, are mapped through a
+//! `Palette` into an RGB `Image`, which can be written as PBM or PGM
+//! (no dependencies needed) or as PNG with the `png` cargo feature.
+//!
+//! Images can also be read back and thresholded into spins (bright pixels
+//! are up) or site masks (dark pixels are set), e.g. to start from a hand
+//! drawn domain shape or a measured domain image.
+//!
+//! ```no_run
+//! use ising_lib::image::Image;
+//! use ising_lib::lattice2d::Lattice2d;
+//!
+//! let lattice = Lattice2d::builder()
+//!     .init_array(Image::load("letter.pgm").unwrap().to_spins(128))
+//!     .vacancies(Image::load("holes.pbm").unwrap().to_mask(128))
+//!     .build()
+//!     .unwrap();
+//! ```
+//!
+//! ```no_run
+//! use ising_lib::image::{FrameWriter, Image, Palette};
+//! use ising_lib::lattice2d::Lattice2d;
+//!
+//! let mut lattice = Lattice2d::new_basic([100, 100]);
+//! Image::from_spins(&lattice.nodes, &Palette::Grayscale, 4).save("start.pgm").unwrap();
+//!
+//! // save a frame every 10 sweeps, as frames/quench_00000.pbm, ...
+//! let mut frames = FrameWriter::new("frames", "quench_", "pbm");
+//! lattice.update_n_with_frames(100 * 10_000, 10, &mut frames).unwrap();
+//! ```
+
+use crate::error::IsingError;
+use crate::lattice2d::{InitType, Lattice2d};
+use ndarray::prelude::*;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Maps values in [0, 1] to colors
+#[derive(Clone, Debug)]
+pub enum Palette {
+    /// Black to white, down spins are black and up spins are white
+    Grayscale,
+    /// Linear interpolation between evenly spaced color stops
+    Gradient(Vec<[u8; 3]>),
+}
+
+impl Palette {
+    /// Blue through white to red, for signed fields
+    pub fn diverging() -> Self {
+        Palette::Gradient(vec![[33, 102, 172], [247, 247, 247], [178, 24, 43]])
+    }
+
+    /// The color of x, clamped to [0, 1]
+    pub fn color(&self, x: f64) -> [u8; 3] {
+        let x = if x.is_nan() { 0.0 } else { x.clamp(0.0, 1.0) };
+        match self {
+            Palette::Grayscale => {
+                let v = (x * 255.0).round() as u8;
+                [v, v, v]
+            }
+            Palette::Gradient(stops) => match stops.len() {
+                0 => [0, 0, 0],
+                1 => stops[0],
+                n => {
+                    let pos = x * (n - 1) as f64;
+                    let i = (pos.floor() as usize).min(n - 2);
+                    let t = pos - i as f64;
+                    let mut rgb = [0u8; 3];
+                    for c in 0..3 {
+                        let (a, b) = (stops[i][c] as f64, stops[i + 1][c] as f64);
+                        rgb[c] = (a + t * (b - a)).round() as u8;
+                    }
+                    rgb
+                }
+            },
+        }
+    }
+}
+
+/// An RGB image, stored row by row
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 3]>,
+}
+
+impl Image {
+    /// Image of a spin configuration, each site drawn as a `scale` x `scale`
+    /// square. Down spins map to the start of the palette and up spins to
+    /// the end, anything else (e.g. vacancies) to the middle.
+    pub fn from_spins(spins: &Array2<i32>, palette: &Palette, scale: usize) -> Self {
+        let field = spins.mapv(|s| match s {
+            1 => 1.0,
+            -1 => 0.0,
+            _ => 0.5,
+        });
+        Image::from_field(field.view(), (0.0, 1.0), palette, scale)
+    }
+
+    /// Image of a scalar field, e.g. Potts states or the angle of continuous
+    /// spins, with values in `range` mapped onto the palette
+    pub fn from_field(field: ArrayView2<f64>, range: (f64, f64), palette: &Palette, scale: usize) -> Self {
+        let scale = scale.max(1);
+        let (rows, cols) = field.dim();
+        let (width, height) = (cols * scale, rows * scale);
+        let span = range.1 - range.0;
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let value = field[[y / scale, x / scale]];
+                let t = if span != 0.0 { (value - range.0) / span } else { 0.5 };
+                pixels.push(palette.color(t));
+            }
+        }
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Thresholds the image into spins, pixels at least as bright as
+    /// `threshold` are up and darker ones are down
+    pub fn to_spins(&self, threshold: u8) -> Array2<i32> {
+        let luma: Vec<u8> = self.luma().collect();
+        Array2::from_shape_fn((self.height, self.width), |(y, x)| {
+            if luma[y * self.width + x] >= threshold {
+                1
+            } else {
+                -1
+            }
+        })
+    }
+
+    /// Thresholds the image into a site mask (e.g. vacancies or pinned
+    /// sites), pixels darker than `threshold` are set
+    pub fn to_mask(&self, threshold: u8) -> Array2<bool> {
+        self.to_spins(threshold).mapv(|s| s == -1)
+    }
+
+    /// Reads an image, the format is chosen by the file extension (`pbm`,
+    /// `pgm`, `ppm` or `pnm`, or `png` with the `png` feature)
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, IsingError> {
+        let path = path.as_ref();
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+        let mut r = BufReader::new(File::open(path)?);
+        match ext.as_deref() {
+            Some("pbm") | Some("pgm") | Some("ppm") | Some("pnm") => Image::read_pnm(&mut r),
+            #[cfg(feature = "png")]
+            Some("png") => Image::read_png(r),
+            #[cfg(not(feature = "png"))]
+            Some("png") => Err(IsingError::Unimplemented("PNG import without the `png` feature")),
+            _ => Err(IsingError::Format(format!(
+                "unknown image format for {}",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Reads a PBM, PGM or PPM image, in either the plain (P1, P2, P3) or
+    /// the binary (P4, P5, P6) variant
+    pub fn read_pnm<R: Read>(r: &mut R) -> Result<Self, IsingError> {
+        let mut bytes = Vec::new();
+        r.read_to_end(&mut bytes)?;
+        let mut pos = 0;
+        let magic = pnm_token(&bytes, &mut pos)?;
+        let kind = match magic.as_slice() {
+            [b'P', kind @ b'1'..=b'6'] => *kind - b'0',
+            _ => return Err(IsingError::Format("not a PBM, PGM or PPM image".to_owned())),
+        };
+        let width = pnm_number(&bytes, &mut pos)?;
+        let height = pnm_number(&bytes, &mut pos)?;
+        let maxval = if kind == 1 || kind == 4 { 1 } else { pnm_number(&bytes, &mut pos)? };
+        if maxval == 0 || maxval > 65535 {
+            return Err(IsingError::Format(format!("invalid maxval {}", maxval)));
+        }
+        let channels = if kind == 3 || kind == 6 { 3 } else { 1 };
+        let n_samples = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(channels))
+            .ok_or_else(|| IsingError::Format("image too large".to_owned()))?;
+        let sample_len = if maxval > 255 { 2 } else { 1 };
+        let scale = |v: usize| ((v.min(maxval) * 255 + maxval / 2) / maxval) as u8;
+
+        // the least data that holds the samples, a plain sample takes at
+        // least a byte; checked before reserving space for the samples
+        let data_len = match kind {
+            1..=3 => Some(n_samples),
+            4 => width.div_ceil(8).checked_mul(height),
+            _ => n_samples.checked_mul(sample_len),
+        };
+        if kind >= 4 {
+            pos += 1; // single whitespace after the header
+        }
+        let data_len = data_len
+            .filter(|&len| len <= bytes.len().saturating_sub(pos))
+            .ok_or_else(|| IsingError::Format("truncated image data".to_owned()))?;
+        let mut samples: Vec<u8> = Vec::with_capacity(n_samples);
+        match kind {
+            1 => {
+                // plain PBM, digits may not be separated by whitespace
+                while samples.len() < n_samples {
+                    match bytes.get(pos) {
+                        Some(b'0') => samples.push(255),
+                        Some(b'1') => samples.push(0), // 1 is black
+                        Some(b'#') => skip_comment(&bytes, &mut pos),
+                        Some(c) if c.is_ascii_whitespace() => {}
+                        _ => return Err(IsingError::Format("truncated PBM".to_owned())),
+                    }
+                    pos += 1;
+                }
+            }
+            2 | 3 => {
+                for _ in 0..n_samples {
+                    samples.push(scale(pnm_number(&bytes, &mut pos)?));
+                }
+            }
+            4 => {
+                let row_len = width.div_ceil(8);
+                let data = &bytes[pos..pos + data_len];
+                for y in 0..height {
+                    for x in 0..width {
+                        let bit = data[y * row_len + x / 8] & (0x80 >> (x % 8));
+                        samples.push(if bit != 0 { 0 } else { 255 });
+                    }
+                }
+            }
+            _ => {
+                let data = &bytes[pos..pos + data_len];
+                for chunk in data.chunks(sample_len) {
+                    let v = chunk.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+                    samples.push(scale(v));
+                }
+            }
+        }
+
+        let pixels = if channels == 3 {
+            samples.chunks(3).map(|c| [c[0], c[1], c[2]]).collect()
+        } else {
+            samples.iter().map(|&v| [v, v, v]).collect()
+        };
+        Ok(Image {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Reads a PNG image of any color type, 16-bit samples are reduced to 8
+    #[cfg(feature = "png")]
+    pub fn read_png<R: Read>(r: R) -> Result<Self, IsingError> {
+        let png_err = |err: png::DecodingError| IsingError::Format(err.to_string());
+        let mut decoder = png::Decoder::new(r);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(png_err)?;
+        let mut data = vec![0u8; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).map_err(png_err)?;
+        let data = &data[..info.buffer_size()];
+        let pixels = match info.color_type {
+            png::ColorType::Grayscale => data.iter().map(|&v| [v, v, v]).collect(),
+            png::ColorType::GrayscaleAlpha => data.chunks(2).map(|c| [c[0], c[0], c[0]]).collect(),
+            png::ColorType::Rgb => data.chunks(3).map(|c| [c[0], c[1], c[2]]).collect(),
+            png::ColorType::Rgba => data.chunks(4).map(|c| [c[0], c[1], c[2]]).collect(),
+            png::ColorType::Indexed => {
+                return Err(IsingError::Format("unexpanded indexed PNG".to_owned()))
+            }
+        };
+        Ok(Image {
+            width: info.width as usize,
+            height: info.height as usize,
+            pixels,
+        })
+    }
+
+    /// Brightness of each pixel in [0, 255]
+    fn luma(&self) -> impl Iterator<Item = u8> + '_ {
+        self.pixels.iter().map(|[r, g, b]| {
+            ((299 * *r as u32 + 587 * *g as u32 + 114 * *b as u32 + 500) / 1000) as u8
+        })
+    }
+
+    /// Writes a binary PBM (P4), pixels darker than mid-gray are black
+    pub fn write_pbm<W: Write>(&self, w: &mut W) -> Result<(), IsingError> {
+        write!(w, "P4\n{} {}\n", self.width, self.height)?;
+        let luma: Vec<u8> = self.luma().collect();
+        let mut row = vec![0u8; self.width.div_ceil(8)];
+        for y in 0..self.height {
+            row.iter_mut().for_each(|byte| *byte = 0);
+            for x in 0..self.width {
+                if luma[y * self.width + x] < 128 {
+                    row[x / 8] |= 0x80 >> (x % 8); // 1 is black
+                }
+            }
+            w.write_all(&row)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a binary PGM (P5) of the pixels' brightness
+    pub fn write_pgm<W: Write>(&self, w: &mut W) -> Result<(), IsingError> {
+        write!(w, "P5\n{} {}\n255\n", self.width, self.height)?;
+        let luma: Vec<u8> = self.luma().collect();
+        w.write_all(&luma)?;
+        Ok(())
+    }
+
+    /// Writes an RGB PNG
+    #[cfg(feature = "png")]
+    pub fn write_png<W: Write>(&self, w: &mut W) -> Result<(), IsingError> {
+        let mut encoder = png::Encoder::new(w, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let png_err = |err: png::EncodingError| IsingError::Format(err.to_string());
+        let mut writer = encoder.write_header().map_err(png_err)?;
+        let data: Vec<u8> = self.pixels.iter().flatten().copied().collect();
+        writer.write_image_data(&data).map_err(png_err)?;
+        Ok(())
+    }
+
+    /// Saves the image, the format is chosen by the file extension
+    /// (`pbm`, `pgm`, or `png` with the `png` feature)
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), IsingError> {
+        let path = path.as_ref();
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+        let write: fn(&Image, &mut BufWriter<File>) -> Result<(), IsingError> = match ext.as_deref() {
+            Some("pbm") => Image::write_pbm,
+            Some("pgm") => Image::write_pgm,
+            #[cfg(feature = "png")]
+            Some("png") => Image::write_png,
+            #[cfg(not(feature = "png"))]
+            Some("png") => return Err(IsingError::Unimplemented("PNG export without the `png` feature")),
+            _ => {
+                return Err(IsingError::Format(format!(
+                    "unknown image format for {}",
+                    path.display()
+                )))
+            }
+        };
+        let mut w = BufWriter::new(File::create(path)?);
+        write(self, &mut w)?;
+        w.flush()?;
+        Ok(())
+    }
+}
+
+/// Skips a `#` comment up to (not past) the end of the line
+fn skip_comment(bytes: &[u8], pos: &mut usize) {
+    while *pos < bytes.len() && bytes[*pos] != b'\n' {
+        *pos += 1;
+    }
+}
+
+/// The next whitespace separated token of a PNM header or plain raster
+fn pnm_token(bytes: &[u8], pos: &mut usize) -> Result<Vec<u8>, IsingError> {
+    loop {
+        match bytes.get(*pos) {
+            Some(b'#') => skip_comment(bytes, pos),
+            Some(c) if c.is_ascii_whitespace() => *pos += 1,
+            Some(_) => break,
+            None => return Err(IsingError::Format("truncated PNM header".to_owned())),
+        }
+    }
+    let start = *pos;
+    while *pos < bytes.len() && !bytes[*pos].is_ascii_whitespace() && bytes[*pos] != b'#' {
+        *pos += 1;
+    }
+    Ok(bytes[start..*pos].to_vec())
+}
+
+fn pnm_number(bytes: &[u8], pos: &mut usize) -> Result<usize, IsingError> {
+    let token = pnm_token(bytes, pos)?;
+    std::str::from_utf8(&token)
+        .ok()
+        .and_then(|t| t.parse().ok())
+        .ok_or_else(|| IsingError::Format("invalid number in PNM image".to_owned()))
+}
+
+/// Initial conditions from images
+impl InitType {
+    /// Starts from the spins of an image thresholded at `threshold`,
+    /// see `Image::to_spins`. The lattice dims must match the image.
+    pub fn from_image<P: AsRef<Path>>(path: P, threshold: u8) -> Result<Self, IsingError> {
+        Ok(InitType::FromArray(Image::load(path)?.to_spins(threshold)))
+    }
+}
+
+/// Receives frames of an evolving lattice, see `Lattice2d::update_n_with_frames`
+pub trait FrameSink {
+    fn frame(&mut self, lattice: &Lattice2d) -> Result<(), IsingError>;
+}
+
+/// Saves each frame as a numbered image file in a directory
+pub struct FrameWriter {
+    pub dir: PathBuf,
+    pub prefix: String,
+    pub extension: String, // pbm, pgm or png
+    pub palette: Palette,
+    pub scale: usize,
+    pub n_frames: usize, // frames written so far, used for numbering
+}
+
+impl FrameWriter {
+    /// Grayscale frames at scale 1, named `{prefix}{00000}.{extension}`
+    pub fn new<P: Into<PathBuf>>(dir: P, prefix: &str, extension: &str) -> Self {
+        FrameWriter {
+            dir: dir.into(),
+            prefix: prefix.to_owned(),
+            extension: extension.to_owned(),
+            palette: Palette::Grayscale,
+            scale: 1,
+            n_frames: 0,
+        }
+    }
+}
+
+impl FrameSink for FrameWriter {
+    fn frame(&mut self, lattice: &Lattice2d) -> Result<(), IsingError> {
+        std::fs::create_dir_all(&self.dir)?;
+        let name = format!("{}{:05}.{}", self.prefix, self.n_frames, self.extension);
+        Image::from_spins(&lattice.nodes, &self.palette, self.scale).save(self.dir.join(name))?;
+        self.n_frames += 1;
+        Ok(())
+    }
+}
+
+/// Recording the evolution of the lattice
+impl Lattice2d {
+    /// Update the lattice by n timesteps, passing a frame to `sink` at the
+    /// start and then every `every_n_sweeps` sweeps (one sweep is `n_sites`
+    /// timesteps)
+    pub fn update_n_with_frames<S: FrameSink>(
+        &mut self,
+        n: usize,
+        every_n_sweeps: usize,
+        sink: &mut S,
+    ) -> Result<(), IsingError> {
+        let interval = every_n_sweeps.max(1) * self.n_sites;
+        sink.frame(self)?;
+        let mut done = 0;
+        while done < n {
+            let steps = interval.min(n - done);
+            self.update_n(steps);
+            done += steps;
+            if steps == interval {
+                sink.frame(self)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn spins() -> Array2<i32> {
+        array![[1, -1, 1], [-1, -1, 1]]
+    }
+
+    #[test]
+    fn test_palette() {
+        assert_eq!(Palette::Grayscale.color(0.0), [0, 0, 0]);
+        assert_eq!(Palette::Grayscale.color(2.0), [255, 255, 255]);
+        let gradient = Palette::Gradient(vec![[0, 0, 0], [200, 100, 0]]);
+        assert_eq!(gradient.color(0.5), [100, 50, 0]);
+        assert_eq!(Palette::diverging().color(0.5), [247, 247, 247]);
+    }
+
+    #[test]
+    fn test_from_spins_scaled() {
+        let image = Image::from_spins(&spins(), &Palette::Grayscale, 2);
+        assert_eq!((image.width, image.height), (6, 4));
+        assert_eq!(image.pixels[0], [255, 255, 255]);
+        assert_eq!(image.pixels[1], [255, 255, 255]);
+        assert_eq!(image.pixels[2], [0, 0, 0]);
+        assert_eq!(image.pixels[6 * 3 + 5], [255, 255, 255]); // row 3, col 5 is site [1, 2]
+    }
+
+    #[test]
+    fn test_write_pbm() {
+        let image = Image::from_spins(&spins(), &Palette::Grayscale, 1);
+        let mut out = Vec::new();
+        image.write_pbm(&mut out).unwrap();
+        let mut expected = b"P4\n3 2\n".to_vec();
+        expected.extend([0b0100_0000, 0b1100_0000]); // down spins are black
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_write_pgm() {
+        let field = array![[0.0, 0.5], [1.0, 2.0]];
+        let image = Image::from_field(field.view(), (0.0, 2.0), &Palette::Grayscale, 1);
+        let mut out = Vec::new();
+        image.write_pgm(&mut out).unwrap();
+        let mut expected = b"P5\n2 2\n255\n".to_vec();
+        expected.extend([0, 64, 128, 255]);
+        assert_eq!(out, expected);
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn test_write_png() {
+        let image = Image::from_spins(&spins(), &Palette::diverging(), 3);
+        let mut out = Vec::new();
+        image.write_png(&mut out).unwrap();
+        assert_eq!(&out[1..4], b"PNG");
+    }
+
+    #[test]
+    fn test_pnm_roundtrip() {
+        let spins = array![[1, -1, 1, 1, -1, 1, 1, 1, -1], [-1, -1, 1, 1, 1, 1, 1, 1, 1]];
+        let image = Image::from_spins(&spins, &Palette::Grayscale, 1);
+        for write in [Image::write_pbm, Image::write_pgm] {
+            let mut out = Vec::new();
+            write(&image, &mut out).unwrap();
+            let read = Image::read_pnm(&mut out.as_slice()).unwrap();
+            assert_eq!(read.to_spins(128), spins);
+        }
+    }
+
+    #[test]
+    fn test_read_plain_pnm() {
+        let pbm = b"P1\n# a comment\n3 2\n010\n1 1 0\n";
+        let image = Image::read_pnm(&mut pbm.as_slice()).unwrap();
+        assert_eq!(image.to_spins(128), array![[1, -1, 1], [-1, -1, 1]]);
+        assert_eq!(image.to_mask(128), array![[false, true, false], [true, true, false]]);
+
+        let pgm = b"P2 2 2 15\n0 15\n7 8\n";
+        let image = Image::read_pnm(&mut pgm.as_slice()).unwrap();
+        assert_eq!(image.to_spins(128), array![[-1, 1], [-1, 1]]);
+
+        let ppm = b"P6 1 1 255\n\xff\x00\x00";
+        let image = Image::read_pnm(&mut ppm.as_slice()).unwrap();
+        assert_eq!(image.pixels, vec![[255, 0, 0]]);
+
+        assert!(Image::read_pnm(&mut b"P5 4 4 255\n\x00".as_slice()).is_err());
+        assert!(Image::read_pnm(&mut b"GIF89a".as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_read_pnm_rejects_huge_headers() {
+        // sizes that overflow, or that the data can't hold, fail before
+        // anything is reserved for them
+        let headers: [&[u8]; 6] = [
+            b"P1 100000 100000\n0 1",
+            b"P2 100000 100000 255\n0 1",
+            b"P3 18446744073709551615 2 255\n0",
+            b"P4 100000 100000\n\x00",
+            b"P5 4294967296 4294967296 255\n\x00",
+            b"P6 3074457345618258603 2 65535\n\x00",
+        ];
+        for header in headers {
+            let result = Image::read_pnm(&mut &header[..]);
+            assert!(matches!(result, Err(IsingError::Format(_))), "{:?}", String::from_utf8_lossy(header));
+        }
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn test_png_roundtrip() {
+        let spins = array![[1, -1, 1], [-1, -1, 1]];
+        let image = Image::from_spins(&spins, &Palette::Grayscale, 2);
+        let mut out = Vec::new();
+        image.write_png(&mut out).unwrap();
+        let read = Image::read_png(out.as_slice()).unwrap();
+        assert_eq!(read, image);
+    }
+
+    #[test]
+    fn test_init_from_image() {
+        let path = std::env::temp_dir().join(format!("ising_lib_init_{}.pbm", std::process::id()));
+        let spins = array![[1, -1, 1], [-1, -1, 1]];
+        Image::from_spins(&spins, &Palette::Grayscale, 1).save(&path).unwrap();
+        let init_type = InitType::from_image(&path, 128).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lattice = Lattice2d::builder().dims([2, 3]).init_type(init_type).build().unwrap();
+        assert_eq!(lattice.nodes, spins);
+    }
+
+    #[test]
+    fn test_update_n_with_frames() {
+        struct Counter(usize);
+        impl FrameSink for Counter {
+            fn frame(&mut self, _: &Lattice2d) -> Result<(), IsingError> {
+                self.0 += 1;
+                Ok(())
+            }
+        }
+        let mut lattice = Lattice2d::new_basic([4, 5]);
+        let mut counter = Counter(0);
+        lattice.update_n_with_frames(20 * 7, 2, &mut counter).unwrap();
+        assert_eq!(counter.0, 1 + 3); // initial frame and after sweeps 2, 4, 6
+    }
+
+    #[test]
+    fn test_frame_writer() {
+        let dir = std::env::temp_dir().join(format!("ising_lib_frames_{}", std::process::id()));
+        let mut frames = FrameWriter::new(&dir, "frame_", "pgm");
+        let mut lattice = Lattice2d::new_basic([3, 3]);
+        lattice.update_n_with_frames(9, 1, &mut frames).unwrap();
+        assert_eq!(frames.n_frames, 2);
+        assert!(dir.join("frame_00001.pgm").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -3,15 +3,31 @@
 //! You can use it with the built-in measurement and monte-carlo types
 //! (refer to examples), or you can just use the lattice. 
 
+use crate::error::{check_finite, IsingError};
 use ndarray::prelude::*;
 use rand::prelude::SliceRandom;
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-/// Update rule options for Lattice 2d (Metropolis only for now)
-#[derive(Clone, Copy)]
+/// Update rule options for Lattice 2d
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum UpdateRule {
-    Metropolis,
-    Glauber,
+    Metropolis, // flip with probability min(1, exp(-beta * dE))
+    Glauber,    // heat bath, flip with probability 1 / (1 + exp(beta * dE))
+}
+
+/// Boundary conditions. The lattice is always a torus, fixed boundaries
+/// are realized by pinning the outermost rows and columns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Boundary {
+    Periodic,  // no sites pinned
+    FixedUp,   // the border rows and columns are pinned up
+    Interface, // the top row is pinned up and the bottom row down, which
+               // forces a horizontal domain wall, periodic left to right
 }
 
 // TODO: implement a hamiltonian type, for different models?
@@ -21,15 +37,48 @@ pub enum UpdateRule {
 // - XY
 /// Types of spin system (SpinHalf only for now)
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum SpinType {
     SpinHalf,
 }
 
-/// Initial condition (Random or AllUp)
-#[derive(Clone, Copy)]
+/// Initial condition of the spins, re-applied by `reset_spins`
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum InitType {
     Random,
     AllUp,
+    AllDown,
+    Checkerboard,               // Néel state, neighbouring spins anti-aligned
+    Stripes { width: usize },   // bands of `width` columns alternating up and down
+    DomainWall,                 // left half up, right half down
+    RandomBiased { p_up: f64 }, // each spin is up with probability p_up
+    FromArray(Array2<i32>),     // a given configuration of -1s and 1s
+}
+
+impl InitType {
+    /// Checks that the initial condition makes sense for a lattice of
+    /// the given dims
+    pub fn validate(&self, dims: &[usize; 2]) -> Result<(), IsingError> {
+        let invalid = |msg: String| Err(IsingError::InvalidInitType(msg));
+        match self {
+            InitType::Stripes { width } if *width == 0 => {
+                invalid("stripe width must be at least 1".to_owned())
+            }
+            InitType::RandomBiased { p_up } if !(0.0..=1.0).contains(p_up) => {
+                invalid(format!("p_up must be in [0, 1], got {}", p_up))
+            }
+            InitType::FromArray(arr) if arr.dim() != (dims[0], dims[1]) => invalid(format!(
+                "initial array has shape {:?}, expected {:?}",
+                arr.dim(),
+                (dims[0], dims[1])
+            )),
+            InitType::FromArray(arr) if arr.iter().any(|&s| s != 1 && s != -1) => {
+                invalid("initial array must only contain -1s and 1s".to_owned())
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 /// A type encapsulating the 2d spin lattice
@@ -39,36 +88,136 @@ pub enum InitType {
 /// opposite edges are considered each other's
 /// neighbours
 ///
+/// The energy, magnetization and neighbour-dot are tracked incrementally
+/// as spins are flipped, so measuring them is O(1). If you write to
+/// `nodes` directly, call `resync_totals` afterwards.
+///
+/// Each lattice owns its random number generator, seeded from entropy
+/// unless `seed` is called, so that runs can be reproduced exactly.
+///
+/// Sites can be diluted (vacant sites hold spin 0 and never flip) or
+/// pinned (they keep their spin), see `set_vacancies` and `set_pinned`.
+///
 /// The 2D lattice type
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "Lattice2dData"))]
 pub struct Lattice2d {
     pub dims: [usize; 2],
-    pub n_sites: i32,       // the number of spin 1/2 sites == dims[0] * dims[1]
+    pub n_sites: usize,     // the number of spin 1/2 sites == dims[0] * dims[1]
     pub nodes: Array2<i32>, // this language generalizes better to other graphs
-    update_rule: UpdateRule,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) spin_sum: i64,            // running ∑ s_i
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) dot_spin_neighbours: i64, // running ∑ s_i * s_j over neighbours
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) rng: ChaCha8Rng,
+    pub(crate) update_rule: UpdateRule,
     pub spin_type: SpinType,
     pub init_type: InitType,
     pub j: f64,    // interaction constant, default 1.0
     pub h: f64,    // external uniform magnetic field, default 0.0
     pub beta: f64, // beta = 1/(k_b * T), defaults to 0.43
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub(crate) vacancies: Option<Array2<bool>>, // true where a site is vacant
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub(crate) pinned: Option<Array2<bool>>, // true where a spin is pinned
 }
 
 impl Clone for Lattice2d {
     fn clone(&self) -> Self {
-        Self::new(
+        let mut lattice = Self::new(
             self.dims,
             self.update_rule,
             self.spin_type,
-            self.init_type,
+            self.init_type.clone(),
             self.j,
             self.h,
             self.beta,
-        )
+        );
+        lattice.vacancies = self.vacancies.clone();
+        lattice.pinned = self.pinned.clone();
+        // the pinned spins are part of the setup, not of the state
+        if let Some(pinned) = &self.pinned {
+            azip!((s in &mut lattice.nodes, &old in &self.nodes, &is_pinned in pinned) if is_pinned { *s = old });
+        }
+        lattice.apply_vacancies();
+        lattice
+    }
+}
+
+/// The serialized form of a lattice, checked and converted into a
+/// `Lattice2d` (with its tracked totals recomputed) on deserialization
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct Lattice2dData {
+    dims: [usize; 2],
+    nodes: Array2<i32>,
+    update_rule: UpdateRule,
+    spin_type: SpinType,
+    init_type: InitType,
+    j: f64,
+    h: f64,
+    beta: f64,
+    #[serde(default)]
+    vacancies: Option<Array2<bool>>,
+    #[serde(default)]
+    pinned: Option<Array2<bool>>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<Lattice2dData> for Lattice2d {
+    type Error = IsingError;
+
+    fn try_from(data: Lattice2dData) -> Result<Self, Self::Error> {
+        let n_sites = n_sites(data.dims)?;
+        if data.nodes.dim() != (data.dims[0], data.dims[1]) {
+            return Err(IsingError::InvalidDims(data.nodes.shape().to_vec()));
+        }
+        // the spins are validated the same way as a given initial condition,
+        // except that vacant sites hold 0
+        let mut spins = data.nodes.clone();
+        if let Some(vacancies) = &data.vacancies {
+            check_mask("vacancies", vacancies, &data.dims)?;
+            spins.zip_mut_with(vacancies, |s, &vacant| if vacant && *s == 0 { *s = 1 });
+        }
+        InitType::FromArray(spins).validate(&data.dims)?;
+        data.init_type.validate(&data.dims)?;
+        if let Some(pinned) = &data.pinned {
+            check_mask("pinned", pinned, &data.dims)?;
+        }
+        check_couplings(data.j, data.h, data.beta)?;
+        let mut lattice = Lattice2d {
+            dims: data.dims,
+            n_sites,
+            nodes: data.nodes,
+            spin_sum: 0,
+            dot_spin_neighbours: 0,
+            rng: ChaCha8Rng::from_entropy(),
+            update_rule: data.update_rule,
+            spin_type: data.spin_type,
+            init_type: data.init_type,
+            j: data.j,
+            h: data.h,
+            beta: data.beta,
+            vacancies: data.vacancies,
+            pinned: data.pinned,
+        };
+        lattice.resync_totals();
+        Ok(lattice)
     }
 }
 
 /// Implement basic methods for the 2d lattice type
 impl Lattice2d {
+    /// Start building a lattice with named parameters, see `Lattice2dBuilder`
+    pub fn builder() -> Lattice2dBuilder {
+        Lattice2dBuilder::default()
+    }
+
     /// Create a new lattice of given dims with specific implementation details
+    ///
+    /// Panics if `init_type` doesn't fit the dims (see `InitType::validate`),
+    /// prefer `Lattice2d::builder` to get an error instead
     pub fn new(
         dims: [usize; 2],
         update_rule: UpdateRule,
@@ -78,21 +227,29 @@ impl Lattice2d {
         h: f64,
         beta: f64,
     ) -> Self {
-        let nodes: Array2<i32> = Lattice2d::init_spins(&init_type, &dims);
+        let mut rng = ChaCha8Rng::from_entropy();
+        let nodes: Array2<i32> = Lattice2d::init_spins(&init_type, &dims, &mut rng);
 
         let (width, height) = nodes.dim();
 
-        Lattice2d {
+        let mut lattice = Lattice2d {
             dims: [width, height],
-            n_sites: width as i32 * height as i32,
-            nodes: nodes, // should it be called notes or sites?
-            update_rule: update_rule,
-            spin_type: spin_type,
-            init_type: init_type,
-            j: j,
-            h: h,
-            beta: beta,
-        }
+            n_sites: width * height,
+            nodes, // should it be called notes or sites?
+            spin_sum: 0,
+            dot_spin_neighbours: 0,
+            rng,
+            update_rule,
+            spin_type,
+            init_type,
+            j,
+            h,
+            beta,
+            vacancies: None,
+            pinned: None,
+        };
+        lattice.resync_totals();
+        lattice
     }
 
     /// Create a new lattice of given dims with randomly generated spins
@@ -109,29 +266,157 @@ impl Lattice2d {
     }
 
     /// initiates the sites to some config (often random) as specified by init_type
-    fn init_spins(init_type: &InitType, dims: &[usize; 2]) -> Array2<i32> {
-        let nodes: Array2<i32>;
+    fn init_spins<R: Rng>(init_type: &InitType, dims: &[usize; 2], rng: &mut R) -> Array2<i32> {
+        if let Err(err) = init_type.validate(dims) {
+            panic!("{}", err);
+        }
         match init_type {
-            InitType::Random => {
-                let mut rng = rand::thread_rng();
-                nodes = Array2::from_shape_fn(*dims, |_| *[-1, 1].choose(&mut rng).unwrap());
+            InitType::Random => Array2::from_shape_fn(*dims, |_| *[-1, 1].choose(rng).unwrap()),
+            InitType::AllUp => Array2::<i32>::ones(*dims),
+            InitType::AllDown => -Array2::<i32>::ones(*dims),
+            InitType::Checkerboard => {
+                Array2::from_shape_fn(*dims, |(i, j)| if (i + j) % 2 == 0 { 1 } else { -1 })
             }
-            InitType::AllUp => {
-                nodes = Array2::<i32>::ones(*dims);
+            InitType::Stripes { width } => {
+                Array2::from_shape_fn(*dims, |(_, j)| if (j / width) % 2 == 0 { 1 } else { -1 })
             }
+            InitType::DomainWall => {
+                Array2::from_shape_fn(*dims, |(_, j)| if j < dims[1] / 2 { 1 } else { -1 })
+            }
+            InitType::RandomBiased { p_up } => {
+                Array2::from_shape_fn(*dims, |_| if rng.gen::<f64>() < *p_up { 1 } else { -1 })
+            }
+            InitType::FromArray(arr) => arr.clone(),
         }
-        return nodes;
     }
 
-    /// resets the sites to some config (often random) as specified by init_type
+    /// resets the sites to some config (often random) as specified by init_type,
+    /// pinned spins keep their values, e.g. those of `set_boundary`
     pub fn reset_spins(&mut self) {
-        self.nodes = Lattice2d::init_spins(&self.init_type, &self.dims);
+        let mut nodes = Lattice2d::init_spins(&self.init_type, &self.dims, &mut self.rng);
+        if let Some(pinned) = &self.pinned {
+            azip!((s in &mut nodes, &old in &self.nodes, &is_pinned in pinned) if is_pinned { *s = old });
+        }
+        self.nodes = nodes;
+        self.apply_vacancies();
     }
 
-    /// Gets the difference in energy from flipping the spin at [idx0,idx1]
-    #[allow(non_snake_case)] // just for this function
-    fn get_dE(&self, idx0: usize, idx1: usize) -> f64 {
-        let neighbour_spin_sum: i32 = self.nodes[[idx0, (idx1 + 1) % self.dims[1]]]
+    /// Sets the vacant (diluted) sites, their spins are set to 0 and stay
+    /// there, also after `reset_spins`. `None` removes all vacancies.
+    /// Replacing existing vacancies resets all the spins.
+    pub fn set_vacancies(&mut self, vacancies: Option<Array2<bool>>) -> Result<(), IsingError> {
+        if let Some(mask) = &vacancies {
+            check_mask("vacancies", mask, &self.dims)?;
+        }
+        let had_vacancies = self.vacancies.is_some();
+        self.vacancies = vacancies;
+        if had_vacancies {
+            self.reset_spins(); // refill the sites that were vacant
+        } else {
+            self.apply_vacancies();
+        }
+        Ok(())
+    }
+
+    /// Sets the pinned sites, which are never flipped by `update`.
+    /// `None` unpins all sites.
+    pub fn set_pinned(&mut self, pinned: Option<Array2<bool>>) -> Result<(), IsingError> {
+        if let Some(mask) = &pinned {
+            check_mask("pinned", mask, &self.dims)?;
+        }
+        self.pinned = pinned;
+        Ok(())
+    }
+
+    /// The vacant sites, if any
+    pub fn vacancies(&self) -> Option<&Array2<bool>> {
+        self.vacancies.as_ref()
+    }
+
+    /// The pinned sites, if any
+    pub fn pinned(&self) -> Option<&Array2<bool>> {
+        self.pinned.as_ref()
+    }
+
+    /// Applies boundary conditions by pinning the border sites (and setting
+    /// their spins), this replaces any previously pinned sites
+    pub fn set_boundary(&mut self, boundary: Boundary) {
+        let [rows, cols] = self.dims;
+        let (pinned, spin): (Array2<bool>, Box<dyn Fn(usize) -> i32>) = match boundary {
+            Boundary::Periodic => {
+                self.pinned = None;
+                return;
+            }
+            Boundary::FixedUp => (
+                Array2::from_shape_fn((rows, cols), |(i, j)| {
+                    i == 0 || j == 0 || i == rows - 1 || j == cols - 1
+                }),
+                Box::new(|_| 1),
+            ),
+            Boundary::Interface => (
+                Array2::from_shape_fn((rows, cols), |(i, _)| i == 0 || i == rows - 1),
+                Box::new(|i| if i == 0 { 1 } else { -1 }),
+            ),
+        };
+        for ((i, j), &is_pinned) in pinned.indexed_iter() {
+            let vacant = self.vacancies.as_ref().is_some_and(|mask| mask[[i, j]]);
+            if is_pinned && !vacant {
+                self.nodes[[i, j]] = spin(i);
+            }
+        }
+        self.pinned = Some(pinned);
+        self.resync_totals();
+    }
+
+    /// The update rule
+    pub fn update_rule(&self) -> UpdateRule {
+        self.update_rule
+    }
+
+    /// Changes the update rule
+    pub fn set_update_rule(&mut self, update_rule: UpdateRule) {
+        self.update_rule = update_rule;
+    }
+
+    /// Whether the site at [idx0,idx1] is vacant or pinned
+    pub(crate) fn is_frozen(&self, idx0: usize, idx1: usize) -> bool {
+        self.vacancies.as_ref().is_some_and(|mask| mask[[idx0, idx1]])
+            || self.pinned.as_ref().is_some_and(|mask| mask[[idx0, idx1]])
+    }
+
+    /// Zeroes the spins on vacant sites and resyncs the tracked totals
+    fn apply_vacancies(&mut self) {
+        if let Some(vacancies) = &self.vacancies {
+            self.nodes.zip_mut_with(vacancies, |s, &vacant| if vacant { *s = 0 });
+        }
+        self.resync_totals();
+    }
+
+    /// Reseeds the lattice's random number generator, the same seed gives
+    /// the same sequence of updates (and random resets)
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+    }
+
+    /// Recomputes the tracked energy and magnetization totals from scratch,
+    /// call this after modifying `nodes` directly
+    pub fn resync_totals(&mut self) {
+        self.spin_sum = self.compute_spin_sum();
+        self.dot_spin_neighbours = self.compute_dot_spin_neighbours();
+    }
+
+    /// Flips the spin at [idx0,idx1] and updates the tracked totals
+    pub fn flip(&mut self, idx0: usize, idx1: usize) {
+        let spin = self.nodes[[idx0, idx1]];
+        // each bond is counted twice in the neighbour dot
+        self.dot_spin_neighbours -= 4 * (spin * self.other_neighbour_spin_sum(idx0, idx1)) as i64;
+        self.spin_sum -= 2 * spin as i64;
+        self.nodes[[idx0, idx1]] = -spin; // something more complicated for spin 3/2
+    }
+
+    /// Sum of the four neighbouring spins of [idx0,idx1] (periodic boundaries)
+    pub(crate) fn neighbour_spin_sum(&self, idx0: usize, idx1: usize) -> i32 {
+        self.nodes[[idx0, (idx1 + 1) % self.dims[1]]]
             + self.nodes[[
                 idx0,
                 match idx1 {
@@ -146,7 +431,21 @@ impl Lattice2d {
                     _ => idx0 - 1,
                 },
                 idx1,
-            ]];
+            ]]
+    }
+
+    /// Sum of the neighbouring spins of [idx0,idx1] that sit on other
+    /// sites; along a dimension of length 1 the site is its own neighbour
+    /// twice, and those terms don't change when it flips
+    fn other_neighbour_spin_sum(&self, idx0: usize, idx1: usize) -> i32 {
+        let n_self = 2 * (self.dims.iter().filter(|&&d| d == 1).count() as i32);
+        self.neighbour_spin_sum(idx0, idx1) - n_self * self.nodes[[idx0, idx1]]
+    }
+
+    /// Gets the difference in energy from flipping the spin at [idx0,idx1]
+    #[allow(non_snake_case)] // just for this function
+    fn get_dE(&self, idx0: usize, idx1: usize) -> f64 {
+        let neighbour_spin_sum: i32 = self.other_neighbour_spin_sum(idx0, idx1);
 
         // two times dot prod of spin w/ it's neighbours
         // this is the energy required to flip
@@ -156,27 +455,31 @@ impl Lattice2d {
 
     /// Update the lattice by one timestep, (=one potential flip)
     pub fn update(&mut self) {
+        // pick a random index
+        let idx0: usize = self.rng.gen::<usize>() % self.dims[0];
+        let idx1: usize = self.rng.gen::<usize>() % self.dims[1];
+        if self.is_frozen(idx0, idx1) {
+            return;
+        }
+        // determine weather to flip or not to flip
+        #[allow(non_snake_case)]
+        let dE: f64 = self.get_dE(idx0, idx1);
         match self.update_rule {
             UpdateRule::Metropolis => {
-                // pick a random index
-                let mut rng = rand::thread_rng();
-                let idx0: usize = rng.gen::<usize>() % self.dims[0];
-                let idx1: usize = rng.gen::<usize>() % self.dims[1];
-                // determine weather to flip or not to flip
-                #[allow(non_snake_case)]
-                let dE: f64 = self.get_dE(idx0, idx1);
                 if dE > 0.0 {
-                    let p: f64 = rng.gen::<f64>(); // random f64 between 0 and 1
+                    let p: f64 = self.rng.gen::<f64>(); // random f64 between 0 and 1
                     if p < (-self.beta * dE).exp() {
-                        self.nodes[[idx0, idx1]] *= -1;
+                        self.flip(idx0, idx1);
                     }
                 } else {
-                    self.nodes[[idx0, idx1]] *= -1; // something more complicated for spin 3/2
+                    self.flip(idx0, idx1);
                 }
             }
             UpdateRule::Glauber => {
-                // not yet implemented
-                panic!("Warning Glauber Rule not yet implemented");
+                let p: f64 = self.rng.gen::<f64>();
+                if p < 1.0 / (1.0 + (self.beta * dE).exp()) {
+                    self.flip(idx0, idx1);
+                }
             }
         }
     }
@@ -188,28 +491,184 @@ impl Lattice2d {
         }
     }
 
-    /// Display lattice in terminal
+    /// Display lattice in terminal, see the `render` module for more options
     pub fn disp_terminal(&self) {
-        let mut string = "----------------\n".to_owned();
-        for idx0 in 0..self.dims[0] {
-            string = string + "|";
-            for idx1 in 0..self.dims[1] {
-                match self.nodes[[idx0, idx1]] {
-                    -1 => {
-                        string = string + " ";
-                    }
-                    1 => {
-                        string = string + "#";
-                    }
-                    _ => {
-                        panic!("Ising lattice is an array of -1s and 1s");
-                    }
-                }
-            }
-            string = string + "|\n";
+        println!("{}", self);
+    }
+}
+
+/// The number of sites of a lattice with these dims, which must be
+/// nonzero and not overflow a usize
+fn n_sites(dims: [usize; 2]) -> Result<usize, IsingError> {
+    match dims[0].checked_mul(dims[1]) {
+        Some(n) if n > 0 => Ok(n),
+        _ => Err(IsingError::InvalidDims(dims.to_vec())),
+    }
+}
+
+/// Checks that j and h are finite and beta is finite and non-negative
+pub(crate) fn check_couplings(j: f64, h: f64, beta: f64) -> Result<(), IsingError> {
+    check_finite("j", j)?;
+    check_finite("h", h)?;
+    check_finite("beta", beta)?;
+    if beta < 0.0 {
+        return Err(IsingError::InvalidParameter {
+            name: "beta",
+            reason: format!("must be non-negative, got {}", beta),
+        });
+    }
+    Ok(())
+}
+
+/// Checks that a site mask has the same shape as the lattice
+fn check_mask(name: &'static str, mask: &Array2<bool>, dims: &[usize; 2]) -> Result<(), IsingError> {
+    if mask.dim() == (dims[0], dims[1]) {
+        Ok(())
+    } else {
+        Err(IsingError::InvalidParameter {
+            name,
+            reason: format!("mask has shape {:?}, expected {:?}", mask.dim(), (dims[0], dims[1])),
+        })
+    }
+}
+
+/// Builds a `Lattice2d` from named parameters, validating them first.
+/// Unset parameters default to the same values as `Lattice2d::new_basic`
+/// on a 25x25 lattice.
+///
+/// ```
+/// use ising_lib::lattice2d::{InitType, Lattice2d};
+///
+/// let lattice = Lattice2d::builder()
+///     .dims([10, 20])
+///     .init_type(InitType::AllUp)
+///     .beta(0.5)
+///     .build()
+///     .unwrap();
+/// assert_eq!(lattice.n_sites, 200);
+///
+/// assert!(Lattice2d::builder().beta(-1.0).build().is_err());
+/// ```
+pub struct Lattice2dBuilder {
+    dims: [usize; 2],
+    update_rule: UpdateRule,
+    spin_type: SpinType,
+    init_type: InitType,
+    j: f64,
+    h: f64,
+    beta: f64,
+    seed: Option<u64>,
+    vacancies: Option<Array2<bool>>,
+    pinned: Option<Array2<bool>>,
+}
+
+impl Default for Lattice2dBuilder {
+    fn default() -> Self {
+        Lattice2dBuilder {
+            dims: [25, 25],
+            update_rule: UpdateRule::Metropolis,
+            spin_type: SpinType::SpinHalf,
+            init_type: InitType::Random,
+            j: 1.0,
+            h: 0.0,
+            beta: 0.43,
+            seed: None,
+            vacancies: None,
+            pinned: None,
+        }
+    }
+}
+
+impl Lattice2dBuilder {
+    /// Dimensions of the lattice, default [25, 25]
+    pub fn dims(mut self, dims: [usize; 2]) -> Self {
+        self.dims = dims;
+        self
+    }
+
+    /// Update rule, default `UpdateRule::Metropolis`
+    pub fn update_rule(mut self, update_rule: UpdateRule) -> Self {
+        self.update_rule = update_rule;
+        self
+    }
+
+    /// Spin type, default `SpinType::SpinHalf`
+    pub fn spin_type(mut self, spin_type: SpinType) -> Self {
+        self.spin_type = spin_type;
+        self
+    }
+
+    /// Initial condition, default `InitType::Random`
+    pub fn init_type(mut self, init_type: InitType) -> Self {
+        self.init_type = init_type;
+        self
+    }
+
+    /// Starts from the given configuration of -1s and 1s, and takes the
+    /// dims from its shape
+    pub fn init_array(mut self, spins: Array2<i32>) -> Self {
+        self.dims = [spins.nrows(), spins.ncols()];
+        self.init_type = InitType::FromArray(spins);
+        self
+    }
+
+    /// Interaction constant, default 1.0
+    pub fn j(mut self, j: f64) -> Self {
+        self.j = j;
+        self
+    }
+
+    /// External uniform magnetic field, default 0.0
+    pub fn h(mut self, h: f64) -> Self {
+        self.h = h;
+        self
+    }
+
+    /// beta = 1/(k_b * T), default 0.43
+    pub fn beta(mut self, beta: f64) -> Self {
+        self.beta = beta;
+        self
+    }
+
+    /// Vacant (diluted) sites, default none
+    pub fn vacancies(mut self, vacancies: Array2<bool>) -> Self {
+        self.vacancies = Some(vacancies);
+        self
+    }
+
+    /// Pinned sites that never flip, default none
+    pub fn pinned(mut self, pinned: Array2<bool>) -> Self {
+        self.pinned = Some(pinned);
+        self
+    }
+
+    /// Seed of the random number generator, default seeded from entropy
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Validates the parameters and creates the lattice
+    pub fn build(self) -> Result<Lattice2d, IsingError> {
+        n_sites(self.dims)?;
+        self.init_type.validate(&self.dims)?;
+        check_couplings(self.j, self.h, self.beta)?;
+        let mut lattice = Lattice2d::new(
+            self.dims,
+            self.update_rule,
+            self.spin_type,
+            self.init_type,
+            self.j,
+            self.h,
+            self.beta,
+        );
+        if let Some(seed) = self.seed {
+            lattice.seed(seed);
+            lattice.reset_spins();
         }
-        string = string + "---------------------";
-        println!("{}", string);
+        lattice.set_vacancies(self.vacancies)?;
+        lattice.set_pinned(self.pinned)?;
+        Ok(lattice)
     }
 }
 
@@ -222,10 +681,7 @@ mod tests {
         let lattice = Lattice2d::new_basic([5, 10]);
         println!("{}", lattice.nodes); // cargo test -- --nocapture
         assert_eq!(lattice.nodes.raw_dim(), lattice.dims);
-        assert_eq!(
-            lattice.n_sites,
-            lattice.dims[0] as i32 * lattice.dims[1] as i32
-        )
+        assert_eq!(lattice.n_sites, lattice.dims[0] * lattice.dims[1])
     }
 
     #[test]
@@ -240,10 +696,7 @@ mod tests {
             0.43, // beta = 1/(k_b * T), defaults to 0.43
         );
         assert_eq!(lattice.nodes.raw_dim(), lattice.dims);
-        assert_eq!(
-            lattice.n_sites,
-            lattice.dims[0] as i32 * lattice.dims[1] as i32
-        )
+        assert_eq!(lattice.n_sites, lattice.dims[0] * lattice.dims[1])
     }
 
     #[test]
@@ -270,20 +723,326 @@ mod tests {
     #[test]
     fn test_init_spins() {
         let nodes: Array2<i32> =
-            Lattice2d::init_spins(&InitType::Random, &[4 as usize, 5 as usize]);
+            Lattice2d::init_spins(&InitType::Random, &[4usize, 5usize], &mut rand::thread_rng());
         let (width, height) = nodes.dim();
         assert_eq!(width, 4usize);
         assert_eq!(height, 5usize);
         assert!(nodes[[3, 4]] == 1 || nodes[[3, 4]] == -1);
         assert!(nodes[[0, 0]] == 1 || nodes[[0, 0]] == -1);
 
-        let nodes: Array2<i32> = Lattice2d::init_spins(&InitType::AllUp, &[2 as usize, 3 as usize]);
+        let nodes: Array2<i32> = Lattice2d::init_spins(&InitType::AllUp, &[2usize, 3usize], &mut rand::thread_rng());
         let (width, height) = nodes.dim();
         assert_eq!(width, 2usize);
         assert_eq!(height, 3usize);
         assert_eq!(nodes[[1, 1]], 1i32);
     }
 
+    #[test]
+    fn test_init_spins_patterns() {
+        let dims = [4usize, 6usize];
+        let rng = &mut rand::thread_rng();
+        let down = Lattice2d::init_spins(&InitType::AllDown, &dims, rng);
+        assert!(down.iter().all(|&s| s == -1));
+
+        let checkerboard = Lattice2d::init_spins(&InitType::Checkerboard, &dims, rng);
+        assert_eq!(checkerboard.sum(), 0);
+        assert_eq!(checkerboard[[0, 0]], 1);
+        assert_eq!(checkerboard[[0, 1]], -1);
+        assert_eq!(checkerboard[[1, 0]], -1);
+
+        let stripes = Lattice2d::init_spins(&InitType::Stripes { width: 2 }, &dims, rng);
+        assert_eq!(stripes.row(3).to_vec(), vec![1, 1, -1, -1, 1, 1]);
+
+        let wall = Lattice2d::init_spins(&InitType::DomainWall, &dims, rng);
+        assert_eq!(wall.row(0).to_vec(), vec![1, 1, 1, -1, -1, -1]);
+
+        let biased = Lattice2d::init_spins(&InitType::RandomBiased { p_up: 1.0 }, &dims, rng);
+        assert!(biased.iter().all(|&s| s == 1));
+        let biased = Lattice2d::init_spins(&InitType::RandomBiased { p_up: 0.0 }, &dims, rng);
+        assert!(biased.iter().all(|&s| s == -1));
+    }
+
+    #[test]
+    fn test_init_from_array() {
+        let arr = array![[1, -1, 1], [-1, -1, 1]];
+        let mut lattice = Lattice2d::new(
+            [2, 3],
+            UpdateRule::Metropolis,
+            SpinType::SpinHalf,
+            InitType::FromArray(arr.clone()),
+            1.0,
+            0.0,
+            0.43,
+        );
+        assert_eq!(lattice.nodes, arr);
+        lattice.update_n(100);
+        lattice.reset_spins(); // reset restores the given configuration
+        assert_eq!(lattice.nodes, arr);
+    }
+
+    #[test]
+    fn test_init_type_validate() {
+        let dims = [2usize, 3usize];
+        assert!(InitType::Random.validate(&dims).is_ok());
+        assert!(InitType::Stripes { width: 0 }.validate(&dims).is_err());
+        assert!(InitType::RandomBiased { p_up: 1.5 }.validate(&dims).is_err());
+        assert!(InitType::FromArray(Array2::ones((3, 2))).validate(&dims).is_err());
+        assert!(InitType::FromArray(Array2::zeros((2, 3))).validate(&dims).is_err());
+        assert!(InitType::FromArray(Array2::ones((2, 3))).validate(&dims).is_ok());
+    }
+
+    #[test]
+    fn test_builder() {
+        let lattice = Lattice2d::builder()
+            .dims([4, 7])
+            .init_type(InitType::AllDown)
+            .j(-1.0)
+            .h(0.5)
+            .beta(1.0)
+            .build()
+            .unwrap();
+        assert_eq!(lattice.dims, [4, 7]);
+        assert_eq!(lattice.n_sites, 28);
+        assert!(lattice.nodes.iter().all(|&s| s == -1));
+        assert_eq!(lattice.j, -1.0);
+
+        let default = Lattice2d::builder().build().unwrap();
+        assert_eq!(default.dims, [25, 25]);
+        assert_eq!(default.beta, 0.43);
+    }
+
+    #[test]
+    fn test_builder_errors() {
+        let err = Lattice2d::builder().dims([0, 5]).build();
+        assert!(matches!(err, Err(IsingError::InvalidDims(_))));
+        let err = Lattice2d::builder().dims([usize::MAX, 2]).build();
+        assert!(matches!(err, Err(IsingError::InvalidDims(_))));
+        let err = Lattice2d::builder().beta(-0.1).build();
+        assert!(matches!(err, Err(IsingError::InvalidParameter { name: "beta", .. })));
+        let err = Lattice2d::builder().h(f64::NAN).build();
+        assert!(matches!(err, Err(IsingError::InvalidParameter { name: "h", .. })));
+        let err = Lattice2d::builder()
+            .init_type(InitType::RandomBiased { p_up: -0.5 })
+            .build();
+        assert!(matches!(err, Err(IsingError::InvalidInitType(_))));
+        let err = Lattice2d::builder()
+            .dims([2, 2])
+            .init_type(InitType::FromArray(Array2::ones((2, 3))))
+            .build();
+        assert!(matches!(err, Err(IsingError::InvalidInitType(_))));
+    }
+
+    #[test]
+    fn test_n_sites() {
+        assert_eq!(n_sites([5, 9]).unwrap(), 45);
+        assert!(n_sites([0, 9]).is_err());
+        assert!(n_sites([usize::MAX, 2]).is_err());
+        // past i32::MAX sites only the memory is the limit
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(n_sites([1 << 16, 1 << 16]).unwrap(), 1 << 32);
+    }
+
+    #[test]
+    fn test_tracked_totals_are_i64() {
+        // totals beyond i32::MAX without the memory for such a lattice
+        let mut lattice = Lattice2d::builder().dims([4, 4]).seed(5).build().unwrap();
+        let offset = 3 * i32::MAX as i64;
+        lattice.spin_sum += offset;
+        lattice.dot_spin_neighbours += offset;
+        lattice.update_n(200);
+        lattice.flip(1, 2);
+        assert_eq!(lattice.spin_sum - offset, lattice.compute_spin_sum());
+        assert_eq!(lattice.dot_spin_neighbours - offset, lattice.compute_dot_spin_neighbours());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_invalid_init_type() {
+        let _lattice = Lattice2d::new(
+            [2, 2],
+            UpdateRule::Metropolis,
+            SpinType::SpinHalf,
+            InitType::FromArray(Array2::ones((3, 3))),
+            1.0,
+            0.0,
+            0.43,
+        );
+    }
+
+    #[test]
+    fn test_tracked_totals() {
+        use crate::measurement::Measurement;
+        let mut lattice = Lattice2d::new_basic([6, 7]);
+        for _ in 0..50 {
+            lattice.update_n(20);
+            assert_eq!(lattice.spin_sum, lattice.compute_spin_sum());
+            assert_eq!(lattice.dot_spin_neighbours, lattice.compute_dot_spin_neighbours());
+        }
+        lattice.flip(0, 0);
+        assert_eq!(lattice.get_spin_sum(), lattice.compute_spin_sum());
+        assert_eq!(lattice.get_dot_spin_neighbours(), lattice.compute_dot_spin_neighbours());
+
+        lattice.nodes.fill(1);
+        lattice.resync_totals();
+        assert_eq!(lattice.get_spin_sum(), 42);
+        assert_eq!(lattice.get_dot_spin_neighbours(), 42 * 4);
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_dims_of_one() {
+        use crate::measurement::Measurement;
+        // a chain, where each site is its own neighbour along the short side
+        for dims in [[1, 6], [6, 1], [1, 1]] {
+            let mut lattice = Lattice2d::builder().dims(dims).seed(1).build().unwrap();
+            for _ in 0..10 {
+                lattice.update_n(50);
+                assert_eq!(lattice.dot_spin_neighbours, lattice.compute_dot_spin_neighbours());
+                assert_eq!(lattice.spin_sum, lattice.compute_spin_sum());
+            }
+        }
+
+        // get_dE is half the energy change of a flip
+        let mut lattice = Lattice2d::builder().dims([1, 6]).seed(2).build().unwrap();
+        for i in 0..6 {
+            let dE = lattice.get_dE(0, i);
+            let before = lattice.measure_energy();
+            lattice.flip(0, i);
+            assert!((lattice.measure_energy() - before - 2.0 * dE).abs() < 1e-9);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_roundtrip() {
+        use crate::measurement::Measurement;
+        let mut lattice = Lattice2d::builder()
+            .dims([5, 4])
+            .init_type(InitType::Stripes { width: 2 })
+            .h(0.25)
+            .build()
+            .unwrap();
+        lattice.update_n(100);
+        let json = serde_json::to_string(&lattice).unwrap();
+        let copy: Lattice2d = serde_json::from_str(&json).unwrap();
+        assert_eq!(copy.nodes, lattice.nodes);
+        assert_eq!(copy.dims, lattice.dims);
+        assert_eq!(copy.h, 0.25);
+        assert_eq!(copy.measure_energy(), lattice.measure_energy());
+
+        // spins that aren't -1 or 1 are rejected
+        let bad = json.replacen("\"data\":[1", "\"data\":[7", 1).replacen("\"data\":[-1", "\"data\":[7", 1);
+        assert!(serde_json::from_str::<Lattice2d>(&bad).is_err());
+
+        // as are spins of another shape than dims
+        let vacancies = Array2::from_shape_fn((4, 4), |(i, j)| i == j);
+        let lattice = Lattice2d::builder().dims([4, 4]).vacancies(vacancies).build().unwrap();
+        let json = serde_json::to_string(&lattice).unwrap();
+        let bad = json.replacen("\"dim\":[4,4]", "\"dim\":[2,8]", 1);
+        assert_ne!(bad, json);
+        assert!(serde_json::from_str::<Lattice2d>(&bad).is_err());
+
+        // and couplings the builder would reject
+        let bad = json.replacen("\"beta\":0.43", "\"beta\":-1.0", 1);
+        assert_ne!(bad, json);
+        assert!(serde_json::from_str::<Lattice2d>(&bad).is_err());
+    }
+
+    #[test]
+    fn test_seed_reproducible() {
+        let build = || Lattice2d::builder().dims([8, 8]).seed(42).build().unwrap();
+        let (mut a, mut b) = (build(), build());
+        assert_eq!(a.nodes, b.nodes);
+        a.update_n(500);
+        b.update_n(500);
+        assert_eq!(a.nodes, b.nodes);
+        a.seed(7);
+        b.seed(7);
+        a.reset_spins();
+        b.reset_spins();
+        assert_eq!(a.nodes, b.nodes);
+    }
+
+    #[test]
+    fn test_vacancies_and_pinning() {
+        use crate::measurement::Measurement;
+        let vacancies = Array2::from_shape_fn((6, 6), |(i, _)| i == 0);
+        let pinned = Array2::from_shape_fn((6, 6), |(i, _)| i == 5);
+        let mut lattice = Lattice2d::builder()
+            .dims([6, 6])
+            .init_type(InitType::AllUp)
+            .vacancies(vacancies)
+            .pinned(pinned)
+            .beta(0.01) // hot, so unpinned spins flip often
+            .build()
+            .unwrap();
+        assert!(lattice.nodes.row(0).iter().all(|&s| s == 0));
+        assert_eq!(lattice.get_spin_sum(), 30);
+        lattice.update_n(5_000);
+        assert!(lattice.nodes.row(0).iter().all(|&s| s == 0));
+        assert!(lattice.nodes.row(5).iter().all(|&s| s == 1));
+        assert!(lattice.nodes.slice(s![1..5, ..]).iter().any(|&s| s == -1));
+
+        let copy = lattice.clone();
+        assert!(copy.nodes.row(0).iter().all(|&s| s == 0));
+        assert!(copy.pinned().is_some());
+
+        lattice.set_vacancies(None).unwrap();
+        assert!(lattice.nodes.iter().all(|&s| s == 1)); // refilled by the reset
+        assert!(lattice.set_pinned(Some(Array2::from_elem((2, 2), true))).is_err());
+    }
+
+    #[test]
+    fn test_glauber() {
+        use crate::measurement::Measurement;
+        let mut lattice = Lattice2d::builder()
+            .dims([10, 10])
+            .update_rule(UpdateRule::Glauber)
+            .init_type(InitType::AllUp)
+            .beta(2.0) // cold, stays ordered
+            .build()
+            .unwrap();
+        lattice.update_n(10_000);
+        assert!(lattice.get_spin_mean() > 0.9);
+
+        lattice.set_update_rule(UpdateRule::Metropolis);
+        assert_eq!(lattice.update_rule(), UpdateRule::Metropolis);
+    }
+
+    #[test]
+    fn test_set_boundary() {
+        let mut lattice = Lattice2d::builder().dims([6, 5]).beta(0.01).build().unwrap();
+        lattice.set_boundary(Boundary::Interface);
+        lattice.update_n(3_000);
+        assert!(lattice.nodes.row(0).iter().all(|&s| s == 1));
+        assert!(lattice.nodes.row(5).iter().all(|&s| s == -1));
+
+        lattice.set_boundary(Boundary::FixedUp);
+        lattice.update_n(3_000);
+        assert!(lattice.nodes.row(5).iter().all(|&s| s == 1));
+        assert!(lattice.nodes.column(0).iter().all(|&s| s == 1));
+        // resetting and cloning keep the boundary
+        lattice.reset_spins();
+        assert!(lattice.nodes.row(0).iter().all(|&s| s == 1));
+        assert!(lattice.nodes.column(4).iter().all(|&s| s == 1));
+        let clone = lattice.clone();
+        assert!(clone.nodes.row(5).iter().all(|&s| s == 1));
+        assert!(clone.nodes.column(0).iter().all(|&s| s == 1));
+
+        lattice.set_boundary(Boundary::Periodic);
+        assert!(lattice.pinned().is_none());
+    }
+
+    #[test]
+    fn test_builder_init_array() {
+        let lattice = Lattice2d::builder()
+            .init_array(array![[1, 1, -1], [-1, 1, 1]])
+            .build()
+            .unwrap();
+        assert_eq!(lattice.dims, [2, 3]);
+        assert_eq!(lattice.nodes[[1, 0]], -1);
+    }
+
     #[test]
     fn test_reset_spins() {
         let mut lattice = Lattice2d::new_basic([5, 10]);
@@ -6,9 +6,22 @@
 //! Refer to the github repository for [examples](https://github.com/micouy/ising_lib). 
 
 
+#[cfg(feature = "gif")]
+pub mod animation;
+pub mod checkpoint;
+pub mod clusters;
+pub mod error;
+#[cfg(feature = "toml")]
+pub mod experiment;
+pub mod graph;
+pub mod image;
 pub mod lattice2d;
 pub mod measurement;
 pub mod monte_carlo_measurement;
+pub mod observable;
+pub mod output;
+pub mod pool;
+pub mod render;
+pub mod sample_set;
 // pub mod prelude; // TODO: do this
-// pub mod graph; // TODO: implement this
 
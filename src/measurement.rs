@@ -4,32 +4,80 @@
 //! multiple samples.
 
 use ndarray::prelude::*;
+use crate::clusters::{ClusterKind, Clusters};
 use crate::lattice2d::*;
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
 
 /// The measurement trait measures quantities across different graphs.
 /// Each method returns a 2d vector of dim (x,y) where x is the number
 /// of simulated graphs run, and y is the number of samples taken with
 /// each run. 
 pub trait Measurement {
-    fn get_spin_sum(&self) -> i32;      // get the sum of the spin values
+    fn get_spin_sum(&self) -> i64;      // get the sum of the spin values
     fn get_spin_mean(&self) -> f64;     // get the mean value of spins 
     fn _convolve_2d_circ_neighbours(mat:&Array2<i32>) -> Array2<i32>; // convolves mat with filt with circular boundary conditions
-    fn get_dot_spin_neighbours(&self) -> i32;   // get dot-product of each spin with the sum of it's neighbours
+    fn get_dot_spin_neighbours(&self) -> i64;   // get dot-product of each spin with the sum of it's neighbours
     fn measure_energy(&self) -> f64;            // get total energy of system
     fn measure_energy_per_spin(&self) -> f64;   // get energy divided by number of sites
-    // IDEAS
-    // spacial correlation function
-        // correlation with immediate and 2nd degree neighbors... more?
-        // is there a fast way to implement this?
+    fn measure_local_field(&self) -> Array2<f64>;  // J * ∑ s_j + h at each site
+    fn measure_local_energy(&self) -> Array2<f64>; // -s_i * (J * ∑ s_j + h) at each site
+    fn measure_correlation_function(&self) -> Array2<f64>; // connected two-point function G(d0, d1)
+    fn measure_radial_correlation(&self) -> Vec<f64>;       // G(r), radially averaged
+    fn measure_structure_factor(&self) -> Array2<f64>;     // S(k) = |FFT(s)|^2 / N
+    fn measure_structure_factor_at(&self, idx: [usize; 2]) -> f64; // S at a single wavevector
+    fn measure_abs_magnetization(&self) -> f64;       // |m|
+    fn measure_magnetization_squared(&self) -> f64;   // m^2
+    fn measure_magnetization_quartic(&self) -> f64;   // m^4
+    fn measure_staggered_magnetization(&self) -> f64; // ∑ (-1)^(i+j) s_ij / N, for antiferromagnets
+    fn measure_sublattice_magnetizations(&self) -> [f64; 2]; // mean spin on the even and odd sublattice
+    fn measure_block_magnetizations(&self, block: usize) -> Array2<f64>; // mean spin of each block x block square
+    fn measure_clusters(&self, kind: ClusterKind) -> Clusters; // Hoshen–Kopelman cluster labels and sizes
+    fn measure_unsatisfied_bonds(&self) -> Array2<i32>; // number of neighbours with the opposite spin, per site
+    fn measure_domain_wall_length(&self) -> usize;      // total number of unsatisfied bonds
+    fn measure_interface_profile(&self) -> Vec<f64>;    // height of the interface in each column
+    fn measure_interface_width(&self) -> f64;           // roughness, the standard deviation of the heights
+}
+
+/// Full (non-incremental) recomputation of the quantities that the
+/// lattice tracks as it is updated
+impl Lattice2d {
+    /// ∑ s_i, recomputed over the whole lattice
+    pub(crate) fn compute_spin_sum(&self) -> i64 {
+        self.nodes.iter().map(|&s| s as i64).sum()
+    }
+
+    /// ∑ (s_i * s_j), recomputed over the whole lattice
+    pub(crate) fn compute_dot_spin_neighbours(&self) -> i64 {
+        // circular boudary convolution with neighbor filter
+        // 0 1 0
+        // 1 0 1
+        // 0 1 0 
+        // dot product of result with all_sites
+        // (There may be room for optimization here... possibly a 2x speed 
+        // up... at the expense of readable code?)
+        // The convolution is done site by site, so that no second lattice
+        // sized array is allocated.
+        let mut dot_spin: i64 = 0;
+        for ((idx0, idx1), &spin) in self.nodes.indexed_iter() {
+            dot_spin += (spin * self.neighbour_spin_sum(idx0, idx1)) as i64;
+        }
+        dot_spin
+    }
 }
 
 /// Implement the measurement trait for the Lattice2d type
 impl Measurement for Lattice2d {
-    /// method returns sum of spins in lattice
+    /// method returns sum of spins in lattice, O(1) since the lattice
+    /// keeps a running total
     /// ∑ s_i
-    fn get_spin_sum(&self) -> i32 {
-        self.nodes.iter()
-            .fold(0 , |acc, &x| acc + x)
+    fn get_spin_sum(&self) -> i64 {
+        debug_assert_eq!(
+            self.spin_sum,
+            self.compute_spin_sum(),
+            "tracked spin sum is stale, call resync_totals after editing nodes"
+        );
+        self.spin_sum
     }
 
     /// method returns mean spin of lattice
@@ -56,23 +104,16 @@ impl Measurement for Lattice2d {
         })
     }
 
-    /// method returns dot of spins with their neighbors
+    /// method returns dot of spins with their neighbors, O(1) since the
+    /// lattice keeps a running total
     /// ∑ (s_i * s_j)   summing over all i,j pairs of neighbors
-    fn get_dot_spin_neighbours(&self) -> i32 {
-        // circular boudary convolution with neighbor filter
-        // 0 1 0
-        // 1 0 1
-        // 0 1 0 
-        // dot product of result with all_sites
-        // (There may be room for optimization here... possibly a 2x speed 
-        // up... at the expense of readable code?)
-        let neighbors = Lattice2d::_convolve_2d_circ_neighbours(&self.nodes);
-        assert_eq!(neighbors.shape() , self.nodes.shape());
-        let mut dot_spin:i32 = 0;
-        for (x,y) in self.nodes.iter().zip(neighbors) {
-            dot_spin += x * y;
-        }
-        dot_spin 
+    fn get_dot_spin_neighbours(&self) -> i64 {
+        debug_assert_eq!(
+            self.dot_spin_neighbours,
+            self.compute_dot_spin_neighbours(),
+            "tracked neighbour dot is stale, call resync_totals after editing nodes"
+        );
+        self.dot_spin_neighbours
     }
     /// Return the energy of the lattice
     ///
@@ -82,14 +123,305 @@ impl Measurement for Lattice2d {
     fn measure_energy(&self) -> f64 {
         let spin_sum = self.get_spin_sum() as f64; // calculate H term
         let spin_neighbours_dot = self.get_dot_spin_neighbours() as f64; // J term
-        // Q: should we take precautions in case of overflow errors here 
-        // when converting from i32 to f64 ? 
-        return - self.j * spin_neighbours_dot - self.h * spin_sum 
+        // the totals are i64, which doesn't overflow for any lattice that
+        // fits in memory, and f64 holds them exactly up to 2^53
+        - self.j * spin_neighbours_dot - self.h * spin_sum
     }
     /// Returns the energy per spin
     fn measure_energy_per_spin(&self) -> f64 {
         self.measure_energy() / self.n_sites as f64 
     }
+
+    /// The effective field acting on each site, from its four
+    /// neighbours (periodic) and the external field
+    ///
+    /// ```text
+    /// h_i = J * ∑ s_j + h
+    /// ```
+    fn measure_local_field(&self) -> Array2<f64> {
+        Lattice2d::_convolve_2d_circ_neighbours(&self.nodes).mapv(|nsum| self.j * nsum as f64 + self.h)
+    }
+
+    /// The energy of each site, -s_i * h_i with h_i the local field, 0 on
+    /// vacancies. Sums to `measure_energy`.
+    fn measure_local_energy(&self) -> Array2<f64> {
+        let mut energy = self.measure_local_field();
+        energy.zip_mut_with(&self.nodes, |e, &s| *e *= -s as f64);
+        energy
+    }
+
+    /// Connected two-point function, G[[d0, d1]] is the correlation of
+    /// spins d0 rows down and d1 columns right of each other (periodic)
+    ///
+    /// ```text
+    /// G(d) = ∑_x s_x * s_(x+d) / N - m^2
+    /// ```
+    ///
+    /// with m the mean spin of this configuration, vacancies count as
+    /// zero spins. E.g. G[[0, 1]] + m^2 is the nearest neighbour
+    /// correlation and G[[k, n]] the (k,n)'th neighbour's. Computed as an
+    /// autocorrelation by FFT, O(N log N).
+    fn measure_correlation_function(&self) -> Array2<f64> {
+        let m = self.get_spin_mean();
+        autocorrelation(&self.nodes).mapv(|c| c - m * m)
+    }
+
+    /// Radially averaged connected correlation function, see
+    /// `radial_average`
+    fn measure_radial_correlation(&self) -> Vec<f64> {
+        radial_average(&self.measure_correlation_function())
+    }
+
+    /// Static structure factor S[[k0, k1]] = |∑_x s_x e^(-i k.x)|^2 / N
+    /// at wavevector k = 2π (k0 / rows, k1 / cols), see `wavevector`.
+    /// S[[0, 0]] = N m^2, and away from k = 0 it is the Fourier transform
+    /// of the connected correlation function.
+    fn measure_structure_factor(&self) -> Array2<f64> {
+        structure_factor(&self.nodes)
+    }
+
+    /// The structure factor at a single wavevector, entry `idx` of
+    /// `measure_structure_factor`, by a direct O(N) sum
+    fn measure_structure_factor_at(&self, idx: [usize; 2]) -> f64 {
+        let [kx, ky] = wavevector(self.dims, idx);
+        let (mut re, mut im) = (0.0, 0.0);
+        for ((i, j), &spin) in self.nodes.indexed_iter() {
+            let phase = kx * i as f64 + ky * j as f64;
+            re += spin as f64 * phase.cos();
+            im -= spin as f64 * phase.sin();
+        }
+        (re * re + im * im) / self.n_sites as f64
+    }
+
+    /// Absolute magnetization per spin, |m|. The signed mean averages to
+    /// zero in a finite system, even in the ordered phase.
+    fn measure_abs_magnetization(&self) -> f64 {
+        self.get_spin_mean().abs()
+    }
+
+    /// m^2, e.g. for the susceptibility N (<m^2> - <|m|>^2) / T
+    fn measure_magnetization_squared(&self) -> f64 {
+        self.get_spin_mean().powi(2)
+    }
+
+    /// m^4, e.g. for the Binder cumulant 1 - <m^4> / (3 <m^2>^2)
+    fn measure_magnetization_quartic(&self) -> f64 {
+        self.get_spin_mean().powi(4)
+    }
+
+    /// Staggered magnetization, the order parameter of the
+    /// antiferromagnet (J < 0), 1 in the Néel state `InitType::Checkerboard`.
+    /// With an odd dimension the sublattices don't fit around the torus.
+    fn measure_staggered_magnetization(&self) -> f64 {
+        let [even, odd] = self.measure_sublattice_magnetizations();
+        let n_even = self.n_sites as f64 - (self.n_sites / 2) as f64; // (0, 0) is even
+        let n_odd = (self.n_sites / 2) as f64;
+        (even * n_even - odd * n_odd) / self.n_sites as f64
+    }
+
+    /// Mean spin of the sites with i + j even and of those with i + j
+    /// odd, the two sublattices of the checkerboard. Vacancies count as
+    /// zero spins.
+    fn measure_sublattice_magnetizations(&self) -> [f64; 2] {
+        let mut sums = [0i64; 2];
+        let mut counts = [0usize; 2];
+        for ((i, j), &spin) in self.nodes.indexed_iter() {
+            sums[(i + j) % 2] += spin as i64;
+            counts[(i + j) % 2] += 1;
+        }
+        [0, 1].map(|k| if counts[k] > 0 { sums[k] as f64 / counts[k] as f64 } else { 0.0 })
+    }
+
+    /// Mean spin of each `block` x `block` square, the blocks at the
+    /// bottom and right edges are cut short if `block` doesn't divide the
+    /// dims. The input of block-spin renormalization and of the local
+    /// order parameter distribution.
+    fn measure_block_magnetizations(&self, block: usize) -> Array2<f64> {
+        let block = block.max(1);
+        let [rows, cols] = self.dims;
+        let shape = (rows.div_ceil(block), cols.div_ceil(block));
+        Array2::from_shape_fn(shape, |(bi, bj)| {
+            let view = self.nodes.slice(s![
+                bi * block..((bi + 1) * block).min(rows),
+                bj * block..((bj + 1) * block).min(cols)
+            ]);
+            view.iter().map(|&s| s as i64).sum::<i64>() as f64 / view.len() as f64
+        })
+    }
+
+    /// Labels the geometric (like spin) or Fortuin–Kasteleyn clusters,
+    /// see `Clusters`. The FK bonds are drawn from a separate stream of
+    /// the lattice's rng, so measuring leaves the updates that follow
+    /// unchanged, and measuring the same state twice gives the same bonds.
+    fn measure_clusters(&self, kind: ClusterKind) -> Clusters {
+        let mut rng = self.rng.clone();
+        rng.set_stream(1);
+        Clusters::label(self, kind, &mut rng)
+    }
+
+    /// The number of neighbours of each site with the opposite spin
+    /// (periodic), 0 on vacancies
+    ///
+    /// ```text
+    /// (|s_i| ∑ |s_j| - s_i ∑ s_j) / 2
+    /// ```
+    fn measure_unsatisfied_bonds(&self) -> Array2<i32> {
+        let occupied = self.nodes.mapv(i32::abs);
+        let occupied_neighbours = Lattice2d::_convolve_2d_circ_neighbours(&occupied);
+        let neighbours = Lattice2d::_convolve_2d_circ_neighbours(&self.nodes);
+        (&occupied * &occupied_neighbours - &self.nodes * &neighbours) / 2
+    }
+
+    /// Total length of the domain walls, in lattice spacings, i.e. the
+    /// number of bonds between opposite spins
+    fn measure_domain_wall_length(&self) -> usize {
+        // each bond is seen from both ends
+        self.measure_unsatisfied_bonds().iter().map(|&n| n as usize).sum::<usize>() / 2
+    }
+
+    /// Height of the interface in each column, for `Boundary::Interface`
+    /// (up spins pinned at the top, down at the bottom). The height is the
+    /// number of down spins in the column, which is where a flat
+    /// interface would be, counted from the bottom; overhangs and bubbles
+    /// are absorbed into it and a vacancy counts as half.
+    fn measure_interface_profile(&self) -> Vec<f64> {
+        self.nodes
+            .columns()
+            .into_iter()
+            .map(|column| column.iter().map(|&s| (1 - s) as f64 / 2.0).sum())
+            .collect()
+    }
+
+    /// Width (roughness) of the interface, the standard deviation of
+    /// `measure_interface_profile` over the columns
+    ///
+    /// ```text
+    /// W^2 = <(h - <h>)^2>
+    /// ```
+    fn measure_interface_width(&self) -> f64 {
+        let heights = self.measure_interface_profile();
+        let n = heights.len() as f64;
+        let mean = heights.iter().sum::<f64>() / n;
+        (heights.iter().map(|h| (h - mean).powi(2)).sum::<f64>() / n).sqrt()
+    }
+}
+
+/// |FFT(s)|^2 / N
+fn structure_factor(nodes: &Array2<i32>) -> Array2<f64> {
+    let mut data = nodes.mapv(|s| Complex::new(s as f64, 0.0));
+    fft_2d(&mut data, false);
+    let n = nodes.len() as f64;
+    data.mapv(|z| z.norm_sqr() / n)
+}
+
+/// ∑_x s_x * s_(x+d) / N for every displacement d, with periodic
+/// boundaries, via the Wiener–Khinchin theorem
+fn autocorrelation(nodes: &Array2<i32>) -> Array2<f64> {
+    let mut data = structure_factor(nodes).mapv(|s| Complex::new(s, 0.0));
+    fft_2d(&mut data, true);
+    // the inverse transform is unnormalized
+    let n = nodes.len() as f64;
+    data.mapv(|z| z.re / n)
+}
+
+/// The wavevector of entry [k0, k1] of a structure factor of a lattice
+/// with the given dims, folded into (-π, π]
+pub fn wavevector(dims: [usize; 2], idx: [usize; 2]) -> [f64; 2] {
+    let fold = |k: usize, n: usize| {
+        let k = if 2 * k > n { k as f64 - n as f64 } else { k as f64 };
+        2.0 * std::f64::consts::PI * k / n as f64
+    };
+    [fold(idx[0], dims[0]), fold(idx[1], dims[1])]
+}
+
+/// Indices of the nonzero wavevectors of smallest length, |k| = 2π / L
+/// with L the larger dimension. These are ±(1, 0) and/or ±(0, 1) in
+/// units of 2π / L, as used by the second-moment correlation length.
+pub fn lowest_wavevectors(dims: [usize; 2]) -> Vec<[usize; 2]> {
+    let [rows, cols] = dims;
+    let l = rows.max(cols);
+    let mut idx = vec![];
+    if rows == l && rows > 1 {
+        idx.push([1, 0]);
+        if rows > 2 {
+            idx.push([rows - 1, 0]);
+        }
+    }
+    if cols == l && cols > 1 {
+        idx.push([0, 1]);
+        if cols > 2 {
+            idx.push([0, cols - 1]);
+        }
+    }
+    idx
+}
+
+/// Averages a structure factor over wavevectors of the same length. Entry
+/// n of the result is S(|k|) at |k| ≈ 2π n / L, with L the smaller
+/// dimension and n up to L / 2 (|k| = π), so that every bin has entries
+/// for any aspect ratio.
+pub fn radial_structure_factor(s: &Array2<f64>) -> Vec<f64> {
+    let (rows, cols) = s.dim();
+    let l = rows.min(cols);
+    let dk = 2.0 * std::f64::consts::PI / l as f64;
+    let n_max = l / 2;
+    let mut sums = vec![0.0; n_max + 1];
+    let mut counts = vec![0usize; n_max + 1];
+    for ((k0, k1), &value) in s.indexed_iter() {
+        let [kx, ky] = wavevector([rows, cols], [k0, k1]);
+        let n = ((kx * kx + ky * ky).sqrt() / dk).round() as usize;
+        if n <= n_max {
+            sums[n] += value;
+            counts[n] += 1;
+        }
+    }
+    sums.iter().zip(counts).map(|(sum, count)| sum / count as f64).collect()
+}
+
+/// In-place 2d FFT (unnormalized), rows then columns
+fn fft_2d(data: &mut Array2<Complex<f64>>, inverse: bool) {
+    let (rows, cols) = data.dim();
+    let mut planner = FftPlanner::new();
+    let plan = |planner: &mut FftPlanner<f64>, len| {
+        if inverse {
+            planner.plan_fft_inverse(len)
+        } else {
+            planner.plan_fft_forward(len)
+        }
+    };
+    let row_fft = plan(&mut planner, cols);
+    for mut row in data.rows_mut() {
+        let mut buffer: Vec<Complex<f64>> = row.to_vec();
+        row_fft.process(&mut buffer);
+        row.assign(&Array1::from(buffer));
+    }
+    let col_fft = plan(&mut planner, rows);
+    for mut col in data.columns_mut() {
+        let mut buffer: Vec<Complex<f64>> = col.to_vec();
+        col_fft.process(&mut buffer);
+        col.assign(&Array1::from(buffer));
+    }
+}
+
+/// Averages G(d0, d1) over displacements of the same length, rounded to
+/// the nearest integer. Displacements are taken as the shortest way
+/// around the torus and r runs up to half the smaller dimension, beyond
+/// that not every direction is covered. Entry r of the result is G(r).
+pub fn radial_average(g: &Array2<f64>) -> Vec<f64> {
+    let (rows, cols) = g.dim();
+    let r_max = rows.min(cols) / 2;
+    let mut sums = vec![0.0; r_max + 1];
+    let mut counts = vec![0usize; r_max + 1];
+    for ((d0, d1), &value) in g.indexed_iter() {
+        let dy = d0.min(rows - d0) as f64;
+        let dx = d1.min(cols - d1) as f64;
+        let r = (dx * dx + dy * dy).sqrt().round() as usize;
+        if r <= r_max {
+            sums[r] += value;
+            counts[r] += 1;
+        }
+    }
+    sums.iter().zip(counts).map(|(sum, count)| sum / count as f64).collect()
 }
 
 #[cfg(test)]
@@ -121,24 +453,24 @@ mod test {
 
     #[test]
     fn test_convolve_2d_circ_neighbours() {
-        let vec1 = vec![
+        let vec1 = [
             vec![0,0,0],
             vec![0,1,0],
             vec![0,0,0],
         ];
-        let vec1_conv = vec![ // we expect vec1 to convolve into this
+        let vec1_conv = [ // we expect vec1 to convolve into this
             vec![0,1,0],
             vec![1,0,1],
             vec![0,1,0],
         ];
-        let vec2 = vec![
+        let vec2 = [
             vec![0,0,0,1],
             vec![0,0,0,0],
             vec![0,0,0,0],
             vec![0,0,0,0],
         ];
         // we expect the convolution operator to turn vec2 into vec2_conv
-        let vec2_conv = vec![
+        let vec2_conv = [
             vec![1,0,1,0],
             vec![0,0,0,1],
             vec![0,0,0,0],
@@ -184,6 +516,265 @@ mod test {
         assert_eq!(lattice.get_dot_spin_neighbours() , 6 * 4);
     }
 
+    #[test]
+    fn test_correlation_function() {
+        let lattice = Lattice2d::builder()
+            .dims([6, 10])
+            .init_type(InitType::RandomBiased { p_up: 0.7 })
+            .seed(5)
+            .build()
+            .unwrap();
+        let g = lattice.measure_correlation_function();
+        assert_eq!(g.dim(), (6, 10));
+        // brute force sum over sites for every displacement
+        let m = lattice.get_spin_mean();
+        let s = &lattice.nodes;
+        for d0 in 0..6 {
+            for d1 in 0..10 {
+                let mut sum = 0;
+                for ((i, j), &spin) in s.indexed_iter() {
+                    sum += spin * s[[(i + d0) % 6, (j + d1) % 10]];
+                }
+                let expected = sum as f64 / 60.0 - m * m;
+                assert!((g[[d0, d1]] - expected).abs() < 1e-9, "{} {}", d0, d1);
+            }
+        }
+        assert!((g[[0, 0]] - (1.0 - m * m)).abs() < 1e-9);
+        // G(0,1) and G(1,0) together give the nearest neighbour correlation
+        let nn = lattice.get_dot_spin_neighbours() as f64 / 60.0 / 4.0;
+        assert!(((g[[0, 1]] + g[[1, 0]]) / 2.0 + m * m - nn).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_structure_factor() {
+        let lattice = Lattice2d::builder()
+            .dims([6, 4])
+            .init_type(InitType::RandomBiased { p_up: 0.3 })
+            .seed(2)
+            .build()
+            .unwrap();
+        let sk = lattice.measure_structure_factor();
+        // direct sum over sites for every wavevector
+        for ((k0, k1), &value) in sk.indexed_iter() {
+            let [kx, ky] = [
+                2.0 * std::f64::consts::PI * k0 as f64 / 6.0,
+                2.0 * std::f64::consts::PI * k1 as f64 / 4.0,
+            ];
+            let (mut re, mut im) = (0.0, 0.0);
+            for ((i, j), &spin) in lattice.nodes.indexed_iter() {
+                let phase = kx * i as f64 + ky * j as f64;
+                re += spin as f64 * phase.cos();
+                im -= spin as f64 * phase.sin();
+            }
+            assert!((value - (re * re + im * im) / 24.0).abs() < 1e-9);
+        }
+        for idx in [[0, 0], [1, 0], [5, 3], [3, 2]] {
+            assert!((lattice.measure_structure_factor_at(idx) - sk[idx]).abs() < 1e-9);
+        }
+        let m = lattice.get_spin_mean();
+        assert!((sk[[0, 0]] - 24.0 * m * m).abs() < 1e-9);
+        // S is the transform of the correlation function, they agree at k = 0
+        let g_sum: f64 = (lattice.measure_correlation_function() + m * m).sum();
+        assert!((sk[[0, 0]] - g_sum).abs() < 1e-9);
+
+        // the Néel state only has weight at (π, π)
+        let neel = Lattice2d::builder().dims([4, 4]).init_type(InitType::Checkerboard).build().unwrap();
+        let sk = neel.measure_structure_factor();
+        assert!((sk[[2, 2]] - 16.0).abs() < 1e-9);
+        assert!((sk.sum() - 16.0).abs() < 1e-9);
+        let radial = radial_structure_factor(&sk);
+        assert_eq!(radial.len(), 3);
+        assert!(radial[..2].iter().all(|x| x.abs() < 1e-9));
+    }
+
+    #[test]
+    fn test_magnetization_moments() {
+        let arr = array![[1, 1, 1], [1, -1, -1]];
+        let lattice = Lattice2d::builder().init_array(arr).build().unwrap();
+        assert!((lattice.get_spin_mean() - 1.0 / 3.0).abs() < 1e-12);
+        let flipped = Lattice2d::builder().init_array(array![[-1, -1, -1], [-1, 1, 1]]).build().unwrap();
+        assert!((flipped.measure_abs_magnetization() - 1.0 / 3.0).abs() < 1e-12);
+        assert!((lattice.measure_magnetization_squared() - 1.0 / 9.0).abs() < 1e-12);
+        assert!((flipped.measure_magnetization_quartic() - 1.0 / 81.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_staggered_and_sublattices() {
+        let neel = Lattice2d::builder().dims([4, 6]).init_type(InitType::Checkerboard).build().unwrap();
+        assert_eq!(neel.get_spin_mean(), 0.0);
+        assert_eq!(neel.measure_staggered_magnetization().abs(), 1.0);
+        let [even, odd] = neel.measure_sublattice_magnetizations();
+        assert_eq!(even, -odd);
+        assert_eq!(even.abs(), 1.0);
+
+        let up = Lattice2d::builder().dims([3, 3]).init_type(InitType::AllUp).build().unwrap();
+        assert_eq!(up.measure_sublattice_magnetizations(), [1.0, 1.0]);
+        assert!((up.measure_staggered_magnetization() - 1.0 / 9.0).abs() < 1e-12); // 5 even, 4 odd
+    }
+
+    #[test]
+    fn test_block_magnetizations() {
+        let lattice = Lattice2d::builder()
+            .dims([3, 6])
+            .init_type(InitType::DomainWall)
+            .build()
+            .unwrap();
+        let blocks = lattice.measure_block_magnetizations(2);
+        assert_eq!(blocks.dim(), (2, 3));
+        assert_eq!(blocks.row(0).to_vec(), vec![1.0, 0.0, -1.0]);
+        assert_eq!(blocks.row(1).to_vec(), vec![1.0, 0.0, -1.0]); // cut short, one row
+        assert_eq!(lattice.measure_block_magnetizations(4).row(0).to_vec(), vec![0.5, -1.0]);
+        let sites = lattice.measure_block_magnetizations(1);
+        assert_eq!(sites, lattice.nodes.mapv(|s| s as f64));
+    }
+
+    #[test]
+    fn test_wavevectors() {
+        let pi = std::f64::consts::PI;
+        assert_eq!(wavevector([4, 8], [0, 1]), [0.0, pi / 4.0]);
+        assert_eq!(wavevector([4, 8], [3, 4]), [-pi / 2.0, pi]);
+        assert_eq!(lowest_wavevectors([8, 8]), vec![[1, 0], [7, 0], [0, 1], [0, 7]]);
+        assert_eq!(lowest_wavevectors([4, 8]), vec![[0, 1], [0, 7]]);
+        assert_eq!(lowest_wavevectors([2, 1]), vec![[1, 0]]);
+    }
+
+    #[test]
+    fn test_radial_correlation() {
+        // stripes of width 1 are anti-correlated along rows only
+        let lattice = Lattice2d::builder()
+            .dims([4, 4])
+            .init_type(InitType::Stripes { width: 1 })
+            .build()
+            .unwrap();
+        let g = lattice.measure_correlation_function();
+        assert!((g[[0, 1]] + 1.0).abs() < 1e-9);
+        assert!((g[[1, 0]] - 1.0).abs() < 1e-9);
+        let radial = lattice.measure_radial_correlation();
+        assert_eq!(radial.len(), 3);
+        assert!((radial[0] - 1.0).abs() < 1e-9);
+        // r = 1 covers (0, ±1) at -1, (±1, 0) at +1 and the four diagonals
+        // at -1, rounded down from 1.41
+        assert!((radial[1] + 0.5).abs() < 1e-9);
+        // r = 2 covers (0, 2) and (2, 0) at +1, (±1, 2) at +1, (2, ±1) at -1
+        assert!((radial[2] - 2.0 / 6.0).abs() < 1e-9);
+    }
+
+
+    #[test]
+    fn test_measure_clusters() {
+        let mut lattice = Lattice2d::builder().dims([10, 10]).beta(0.44).seed(4).build().unwrap();
+        lattice.update_n(1_000);
+        let geometric = lattice.measure_clusters(ClusterKind::Geometric);
+        assert_eq!(geometric.sizes.iter().sum::<usize>(), 100);
+        let fk = lattice.measure_clusters(ClusterKind::FortuinKasteleyn);
+        assert_eq!(fk, lattice.measure_clusters(ClusterKind::FortuinKasteleyn));
+        assert!(fk.n_clusters() >= geometric.n_clusters());
+        // new bonds once the rng has moved on
+        lattice.update_n(1);
+        assert_ne!(fk.labels, lattice.measure_clusters(ClusterKind::FortuinKasteleyn).labels);
+    }
+
+    #[test]
+    fn test_domain_walls() {
+        let lattice = Lattice2d::builder()
+            .dims([3, 4])
+            .init_array(array![[1, 1, -1, -1], [1, 1, -1, -1], [1, 1, 1, 1]])
+            .build()
+            .unwrap();
+        assert_eq!(
+            lattice.measure_unsatisfied_bonds(),
+            array![[1, 1, 2, 2], [1, 1, 2, 2], [0, 0, 2, 2]]
+        );
+        // two vertical walls of length 2 (one around the torus) and two
+        // horizontal ones of length 2 below and above the down block
+        assert_eq!(lattice.measure_domain_wall_length(), 8);
+        // all down, no walls, and vacancies have no bonds
+        let mut lattice = Lattice2d::builder().dims([4, 4]).init_type(InitType::AllDown).build().unwrap();
+        assert_eq!(lattice.measure_domain_wall_length(), 0);
+        lattice.nodes[[1, 1]] = 0;
+        lattice.nodes[[1, 2]] = 1;
+        lattice.resync_totals();
+        assert_eq!(lattice.measure_unsatisfied_bonds()[[1, 1]], 0);
+        assert_eq!(lattice.measure_domain_wall_length(), 3);
+        // for ±1 spins the walls are N - ∑ s_i s_j / 4
+        let lattice = Lattice2d::builder().dims([8, 6]).seed(3).build().unwrap();
+        let expected = lattice.n_sites as i64 - lattice.get_dot_spin_neighbours() / 4;
+        assert_eq!(lattice.measure_domain_wall_length() as i64, expected);
+    }
+
+    #[test]
+    fn test_interface() {
+        let mut lattice = Lattice2d::builder().dims([6, 4]).build().unwrap();
+        lattice.set_boundary(Boundary::Interface);
+        lattice.nodes.assign(&array![
+            [1, 1, 1, 1],
+            [1, 1, 1, 1],
+            [1, 1, -1, 1],
+            [-1, 1, -1, 1],
+            [-1, -1, -1, -1],
+            [-1, -1, -1, -1],
+        ]);
+        lattice.resync_totals();
+        assert_eq!(lattice.measure_interface_profile(), vec![3.0, 2.0, 4.0, 2.0]);
+        // mean 2.75, deviations 0.25, -0.75, 1.25, -0.75
+        assert!((lattice.measure_interface_width() - (0.6875f64).sqrt()).abs() < 1e-12);
+        // a flat interface has no width
+        lattice.set_boundary(Boundary::Interface);
+        lattice.nodes.slice_mut(s![..3, ..]).fill(1);
+        lattice.nodes.slice_mut(s![3.., ..]).fill(-1);
+        lattice.resync_totals();
+        assert_eq!(lattice.measure_interface_profile(), vec![3.0; 4]);
+        assert_eq!(lattice.measure_interface_width(), 0.0);
+        assert_eq!(lattice.measure_domain_wall_length(), 8); // and the wall around the torus
+    }
+
+    #[test]
+    fn test_local_field_and_energy() {
+        let mut vacancies = Array2::from_elem((3, 3), false);
+        vacancies[[2, 2]] = true;
+        let mut lattice = Lattice2d::builder()
+            .dims([3, 3])
+            .init_array(array![[1, 1, 1], [1, -1, 1], [1, 1, 1]])
+            .vacancies(vacancies)
+            .j(0.5)
+            .h(0.25)
+            .build()
+            .unwrap();
+        let field = lattice.measure_local_field();
+        assert_eq!(field[[1, 1]], 0.5 * 4.0 + 0.25);
+        assert_eq!(field[[0, 1]], 0.5 * 2.0 + 0.25); // the flipped spin below cancels one neighbour
+        let energy = lattice.measure_local_energy();
+        assert_eq!(energy[[1, 1]], 2.25); // the flipped spin in the middle is a hot spot
+        assert_eq!(energy[[2, 2]], 0.0); // vacant
+        assert!((energy.sum() - lattice.measure_energy()).abs() < 1e-12);
+
+        lattice.update_n(100);
+        assert!((lattice.measure_local_energy().sum() - lattice.measure_energy()).abs() < 1e-9);
+    }
+
+    /// 4 N outgrows i32 at N > 2^29 sites. The lattice alone takes about
+    /// 2.2 GB, run with `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_totals_beyond_i32() {
+        let mut lattice = Lattice2d::builder()
+            .dims([23_200, 23_200])
+            .init_type(InitType::AllUp)
+            .build()
+            .unwrap();
+        let n = lattice.n_sites as i64;
+        assert!(4 * n > i32::MAX as i64);
+        assert_eq!(lattice.get_spin_sum(), n);
+        assert_eq!(lattice.get_dot_spin_neighbours(), 4 * n);
+        assert_eq!(lattice.measure_energy(), -4.0 * n as f64);
+        assert_eq!(lattice.get_spin_mean(), 1.0);
+        lattice.flip(0, 0);
+        lattice.flip(23_199, 1);
+        assert_eq!(lattice.get_spin_sum(), n - 4);
+        assert_eq!(lattice.get_dot_spin_neighbours(), 4 * n - 32);
+        assert_eq!(lattice.get_dot_spin_neighbours(), lattice.compute_dot_spin_neighbours());
+        assert_eq!(lattice.measure_magnetization_squared(), (1.0 - 4.0 / n as f64).powi(2));
+    }
 }
 
 
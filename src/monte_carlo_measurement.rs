@@ -2,11 +2,21 @@
 //! estimated over multiple runs, (such as the expected value of the 
 //! magnetization squared) 
 
+use crate::clusters::{ClusterKind, Wrapping};
+use crate::error::IsingError;
 use crate::lattice2d::*;
-use crate::measurement::Measurement; 
-use std::thread;
+use crate::measurement::{lowest_wavevectors, radial_average, radial_structure_factor, wavevector, Measurement};
+use crate::observable::{Observable, Quantity};
+use crate::pool;
+use crate::sample_set::{sample_sets, sublattice_sets, BlockSampleSet, SampleSet, Stats};
+use ndarray::prelude::*;
+use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 /// Parameters for monte carlo sampling
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MonteCarloParams {
     pub n_runs: usize,                        // number of dry runs
     pub flips_to_skip: usize,                 // skip flips for system to cool
@@ -16,17 +26,32 @@ pub struct MonteCarloParams {
 
 /// The measurement trait samples quantities across lattices and graphs
 pub trait MonteCarlo {
-    /// Calculates and returns basic metrics by Monto Carlo sampling
-    /// - Energy fluctuations
-    /// - Avg Magnetic Susceptibility
-    fn sample_energy_parallel(&mut self, params: &MonteCarloParams) -> Vec<Vec<f64>>;
-    fn sample_energy(&mut self, params: &MonteCarloParams) -> Vec<Vec<f64>>;
-    fn sample_neighbor_correlations_parallel(&mut self, params: &MonteCarloParams) -> Vec<Vec<f64>>;
-    fn sample_neighbor_correlations(&mut self, params: &MonteCarloParams) -> Vec<Vec<f64>>;
-    fn sample_magnetization_parallel(&mut self, params: &MonteCarloParams) -> Vec<Vec<f64>>;
-    fn sample_magnetization(&mut self, params: &MonteCarloParams) -> Vec<Vec<f64>>;
-    // TODO: implement below function
-    // fn sample_estimate_all_metrics(&self , params:MonteCarloParams) -> Vec;
+    /// Samples any set of observables in a single pass, one `SampleSet`
+    /// per observable in the given order
+    fn sample_observables(&mut self, observables: &[&dyn Observable], params: &MonteCarloParams) -> Vec<SampleSet>;
+    fn sample_observables_parallel(&mut self, observables: &[&dyn Observable], params: &MonteCarloParams, n_threads: usize) -> Result<Vec<SampleSet>, IsingError>;
+    fn sample_energy_parallel(&mut self, params: &MonteCarloParams, n_threads: usize) -> Result<SampleSet, IsingError>;
+    fn sample_energy(&mut self, params: &MonteCarloParams) -> SampleSet;
+    fn sample_neighbor_correlations_parallel(&mut self, params: &MonteCarloParams, n_threads: usize) -> Result<SampleSet, IsingError>;
+    fn sample_neighbor_correlations(&mut self, params: &MonteCarloParams) -> SampleSet;
+    fn sample_magnetization_parallel(&mut self, params: &MonteCarloParams, n_threads: usize) -> Result<SampleSet, IsingError>;
+    fn sample_magnetization(&mut self, params: &MonteCarloParams) -> SampleSet;
+    fn sample_correlation_function(&mut self, params: &MonteCarloParams) -> Result<CorrelationEstimate, IsingError>;
+    fn sample_correlation_function_parallel(&mut self, params: &MonteCarloParams, n_threads: usize) -> Result<CorrelationEstimate, IsingError>;
+    fn sample_structure_factor(&mut self, params: &MonteCarloParams) -> Result<CorrelationEstimate, IsingError>;
+    fn sample_structure_factor_parallel(&mut self, params: &MonteCarloParams, n_threads: usize) -> Result<CorrelationEstimate, IsingError>;
+    fn sample_correlation_length(&mut self, params: &MonteCarloParams) -> Result<CorrelationLength, IsingError>;
+    fn sample_correlation_length_parallel(&mut self, params: &MonteCarloParams, n_threads: usize) -> Result<CorrelationLength, IsingError>;
+    fn sample_quantity(&mut self, quantity: Quantity, params: &MonteCarloParams) -> SampleSet;
+    fn sample_quantity_parallel(&mut self, quantity: Quantity, params: &MonteCarloParams, n_threads: usize) -> Result<SampleSet, IsingError>;
+    fn sample_sublattice_magnetizations(&mut self, params: &MonteCarloParams) -> [SampleSet; 2];
+    fn sample_sublattice_magnetizations_parallel(&mut self, params: &MonteCarloParams, n_threads: usize) -> Result<[SampleSet; 2], IsingError>;
+    fn sample_block_magnetizations(&mut self, params: &MonteCarloParams, block: usize) -> BlockSampleSet;
+    fn sample_block_magnetizations_parallel(&mut self, params: &MonteCarloParams, block: usize, n_threads: usize) -> Result<BlockSampleSet, IsingError>;
+    fn sample_cluster_sizes(&mut self, params: &MonteCarloParams, kind: ClusterKind) -> ClusterSizes;
+    fn sample_cluster_sizes_parallel(&mut self, params: &MonteCarloParams, kind: ClusterKind, n_threads: usize) -> Result<ClusterSizes, IsingError>;
+    fn sample_wrapping_probability(&mut self, params: &MonteCarloParams, kind: ClusterKind, temperatures: &[f64]) -> Vec<WrappingProbability>;
+    fn sample_wrapping_probability_parallel(&mut self, params: &MonteCarloParams, kind: ClusterKind, temperatures: &[f64], n_threads: usize) -> Result<Vec<WrappingProbability>, IsingError>;
 }
 
 /// Implements the measurement trait for the Lattice2d type
@@ -59,174 +84,931 @@ impl MonteCarlo for Lattice2d {
     //     //
     //     // Return avg magnetization, fluctuations, uncertainty
     // }
+    /// Samples every observable in a single pass, each sample measures
+    /// all of them on the same configuration. Returns a `SampleSet` per
+    /// observable, in the given order, with a vec of samples for each of
+    /// the params.n_runs runs.
+    fn sample_observables(&mut self, observables: &[&dyn Observable], params: &MonteCarloParams) -> Vec<SampleSet> {
+        let samples = serial(sample_series(self, params, SERIAL, |lattice: &Lattice2d| measure_all(observables, lattice)));
+        sample_sets(observables, self, params, samples)
+    }
+
+    /// `sample_observables` on a pool of `n_threads` workers, see the
+    /// `pool` module. Each run is seeded from the lattice's rng, so that
+    /// with a seeded lattice the serial and parallel samplers return the
+    /// same samples.
+    fn sample_observables_parallel(&mut self, observables: &[&dyn Observable], params: &MonteCarloParams, n_threads: usize) -> Result<Vec<SampleSet>, IsingError> {
+        let samples = sample_series(self, params, Some(n_threads), |lattice: &Lattice2d| measure_all(observables, lattice))?;
+        Ok(sample_sets(observables, self, params, samples))
+    }
+
     /// Monte Carlo sample of energy
-    /// Returns a vec of energy samples, of length 
-    /// params.n_runs * params.samples_per_run
-    fn sample_energy(&mut self, params: &MonteCarloParams) -> Vec<Vec<f64>> {
-        let mut energy = vec![vec![0.0; params.samples_per_run]; params.n_runs];
-        for i in 0..params.n_runs {
-            self.reset_spins();
-            // Time evolve the system to cool (or heat) it
-            self.update_n(params.flips_to_skip);
-            for j in 0..params.samples_per_run {
-                // Time evolve the system a bit
-                self.update_n(params.flips_to_skip_between_samples);
-                energy[i][j] = self.measure_energy();
-            }
-        }
-        return energy;
+    /// Returns the energy samples, params.samples_per_run for each of
+    /// the params.n_runs runs
+    fn sample_energy(&mut self, params: &MonteCarloParams) -> SampleSet {
+        self.sample_quantity(Quantity::Energy, params)
     }
 
     /// Monte Carlo sample of energy in parallel
-    /// Returns a vec of energy samples, of length 
-    /// params.n_runs * params.samples_per_run
-    fn sample_energy_parallel(&mut self, params: &MonteCarloParams) -> Vec<Vec<f64>> {
-        // let mut energy = vec![vec![0.0; params.samples_per_run]; params.n_runs]; // all samples
-        let mut energy = vec![];
-        let mut fetch_handle = vec![];
-        for _ in 0..params.n_runs {
-            // Create a clone: inits new lattice with same input params
-            let mut lattice_copy = self.clone();
-            let flips_to_skip = params.flips_to_skip;
-            let flips_to_skip_between_samples = params.flips_to_skip_between_samples;
-            let samples_per_run = params.samples_per_run;
-            // Time evolve the system to cool (or heat) it
-            fetch_handle.push(thread::spawn(move || -> Vec<f64> {
-                lattice_copy.update_n(flips_to_skip);
-                let mut erg_samples = vec![];
-                for _ in 0..samples_per_run {
-                    // Time evolve the system a bit
-                    lattice_copy.update_n(flips_to_skip_between_samples);
-                    erg_samples.push(lattice_copy.measure_energy());
-                }
-                erg_samples
-            }));
+    /// Returns the energy samples, params.samples_per_run for each of
+    /// the params.n_runs runs
+    fn sample_energy_parallel(&mut self, params: &MonteCarloParams, n_threads: usize) -> Result<SampleSet, IsingError> {
+        self.sample_quantity_parallel(Quantity::Energy, params, n_threads)
+    }
+
+    /// Monte Carlo estimate of nearest neighbor correlations
+    /// Returns params.samples_per_run samples for each of the
+    /// params.n_runs runs
+    fn sample_neighbor_correlations(&mut self, params: &MonteCarloParams) -> SampleSet {
+        self.sample_quantity(Quantity::NeighborCorrelation, params)
+    }
+
+    /// Monte Carlo estimate of nearest neighbor correlations
+    /// Returns params.samples_per_run samples for each of the
+    /// params.n_runs runs
+    fn sample_neighbor_correlations_parallel(&mut self, params: &MonteCarloParams, n_threads: usize) -> Result<SampleSet, IsingError> {
+        self.sample_quantity_parallel(Quantity::NeighborCorrelation, params, n_threads)
+    }
+
+    /// Monte Carlo sample the magnetization in parallel
+    /// Returns params.samples_per_run samples for each of the
+    /// params.n_runs runs
+    fn sample_magnetization_parallel(&mut self, params: &MonteCarloParams, n_threads: usize) -> Result<SampleSet, IsingError> {
+        self.sample_quantity_parallel(Quantity::Magnetization, params, n_threads)
+    }
+
+    /// Monte Carlo sample the magnetization
+    /// Returns params.samples_per_run samples for each of the
+    /// params.n_runs runs
+    fn sample_magnetization(&mut self, params: &MonteCarloParams) -> SampleSet {
+        self.sample_quantity(Quantity::Magnetization, params)
+    }
+
+    /// Monte Carlo estimate of the connected correlation function
+    /// G(d0, d1) and its radial average G(r), see
+    /// `Measurement::measure_correlation_function`. G is averaged over
+    /// the samples of each run, the error bars come from the spread of
+    /// these run averages.
+    fn sample_correlation_function(&mut self, params: &MonteCarloParams) -> Result<CorrelationEstimate, IsingError> {
+        let run_means = sample_run_means(self, params, SERIAL, Lattice2d::measure_correlation_function)?;
+        Ok(CorrelationEstimate::from_run_means(run_means, radial_average))
+    }
+
+    /// Monte Carlo estimate of the correlation function in parallel, see
+    /// `sample_correlation_function`
+    fn sample_correlation_function_parallel(&mut self, params: &MonteCarloParams, n_threads: usize) -> Result<CorrelationEstimate, IsingError> {
+        let run_means = sample_run_means(self, params, Some(n_threads), Lattice2d::measure_correlation_function)?;
+        Ok(CorrelationEstimate::from_run_means(run_means, radial_average))
+    }
+
+    /// Monte Carlo estimate of the structure factor S(k) and its radial
+    /// average S(|k|), see `Measurement::measure_structure_factor` and
+    /// `radial_structure_factor`
+    fn sample_structure_factor(&mut self, params: &MonteCarloParams) -> Result<CorrelationEstimate, IsingError> {
+        let run_means = sample_run_means(self, params, SERIAL, Lattice2d::measure_structure_factor)?;
+        Ok(CorrelationEstimate::from_run_means(run_means, radial_structure_factor))
+    }
+
+    /// Monte Carlo estimate of the structure factor in parallel, see
+    /// `sample_structure_factor`
+    fn sample_structure_factor_parallel(&mut self, params: &MonteCarloParams, n_threads: usize) -> Result<CorrelationEstimate, IsingError> {
+        let run_means = sample_run_means(self, params, Some(n_threads), Lattice2d::measure_structure_factor)?;
+        Ok(CorrelationEstimate::from_run_means(run_means, radial_structure_factor))
+    }
+
+    /// Monte Carlo estimate of the second-moment correlation length ξ₂,
+    /// see `CorrelationLength`. Each sample measures the magnetic
+    /// susceptibility at k = 0 and at the lowest nonzero wavevectors, the
+    /// jackknife error leaves out one run at a time.
+    fn sample_correlation_length(&mut self, params: &MonteCarloParams) -> Result<CorrelationLength, IsingError> {
+        let run_means = sample_run_means(self, params, SERIAL, susceptibilities)?;
+        Ok(CorrelationLength::from_run_means(self.dims, &run_means))
+    }
+
+    /// Monte Carlo estimate of ξ₂ in parallel, see `sample_correlation_length`
+    fn sample_correlation_length_parallel(&mut self, params: &MonteCarloParams, n_threads: usize) -> Result<CorrelationLength, IsingError> {
+        let run_means = sample_run_means(self, params, Some(n_threads), susceptibilities)?;
+        Ok(CorrelationLength::from_run_means(self.dims, &run_means))
+    }
+
+    /// Monte Carlo sample of any scalar `Quantity`, e.g. |m| or m⁴
+    /// Returns the samples, labelled with the name of the quantity
+    fn sample_quantity(&mut self, quantity: Quantity, params: &MonteCarloParams) -> SampleSet {
+        self.sample_observables(&[&quantity], params).remove(0)
+    }
+
+    /// Monte Carlo sample of a scalar `Quantity` on the worker pool
+    fn sample_quantity_parallel(&mut self, quantity: Quantity, params: &MonteCarloParams, n_threads: usize) -> Result<SampleSet, IsingError> {
+        Ok(self.sample_observables_parallel(&[&quantity], params, n_threads)?.remove(0))
+    }
+
+    /// Monte Carlo sample of the magnetizations of the even and odd
+    /// sublattices, see `Measurement::measure_sublattice_magnetizations`.
+    /// Returns the `sublattice_even` and `sublattice_odd` sample sets.
+    fn sample_sublattice_magnetizations(&mut self, params: &MonteCarloParams) -> [SampleSet; 2] {
+        let samples = serial(sample_series(self, params, SERIAL, Lattice2d::measure_sublattice_magnetizations));
+        sublattice_sets(self, params, samples)
+    }
+
+    /// Monte Carlo sample of the sublattice magnetizations in parallel
+    fn sample_sublattice_magnetizations_parallel(&mut self, params: &MonteCarloParams, n_threads: usize) -> Result<[SampleSet; 2], IsingError> {
+        let samples = sample_series(self, params, Some(n_threads), Lattice2d::measure_sublattice_magnetizations)?;
+        Ok(sublattice_sets(self, params, samples))
+    }
+
+    /// Monte Carlo sample of the magnetizations of `block` x `block`
+    /// blocks, see `Measurement::measure_block_magnetizations`
+    fn sample_block_magnetizations(&mut self, params: &MonteCarloParams, block: usize) -> BlockSampleSet {
+        let samples = serial(sample_series(self, params, SERIAL, move |lattice: &Lattice2d| lattice.measure_block_magnetizations(block)));
+        BlockSampleSet::new(block, self, params, samples)
+    }
+
+    /// Monte Carlo sample of the block magnetizations in parallel
+    fn sample_block_magnetizations_parallel(&mut self, params: &MonteCarloParams, block: usize, n_threads: usize) -> Result<BlockSampleSet, IsingError> {
+        let samples = sample_series(self, params, Some(n_threads), move |lattice: &Lattice2d| lattice.measure_block_magnetizations(block))?;
+        Ok(BlockSampleSet::new(block, self, params, samples))
+    }
+
+    /// Monte Carlo estimate of the cluster size distribution n_s and the
+    /// largest cluster fraction, see `Measurement::measure_clusters`
+    fn sample_cluster_sizes(&mut self, params: &MonteCarloParams, kind: ClusterKind) -> ClusterSizes {
+        ClusterSizes::from_samples(&serial(sample_series(self, params, SERIAL, move |lattice: &Lattice2d| cluster_sizes(lattice, kind))))
+    }
+
+    /// Monte Carlo estimate of the cluster sizes in parallel, see
+    /// `sample_cluster_sizes`
+    fn sample_cluster_sizes_parallel(&mut self, params: &MonteCarloParams, kind: ClusterKind, n_threads: usize) -> Result<ClusterSizes, IsingError> {
+        let samples = sample_series(self, params, Some(n_threads), move |lattice: &Lattice2d| cluster_sizes(lattice, kind))?;
+        Ok(ClusterSizes::from_samples(&samples))
+    }
+
+    /// Monte Carlo estimate of the probability that a cluster wraps
+    /// around the lattice, at each of the temperatures (the lattice's
+    /// beta is restored afterwards), see `Clusters::wrapping`. E.g. the
+    /// FK wrapping probabilities of different sizes cross at Tc, at
+    /// universal values.
+    fn sample_wrapping_probability(&mut self, params: &MonteCarloParams, kind: ClusterKind, temperatures: &[f64]) -> Vec<WrappingProbability> {
+        serial(wrapping_probabilities(self, params, kind, temperatures, SERIAL))
+    }
+
+    /// Monte Carlo estimate of the wrapping probability in parallel, see
+    /// `sample_wrapping_probability`
+    fn sample_wrapping_probability_parallel(&mut self, params: &MonteCarloParams, kind: ClusterKind, temperatures: &[f64], n_threads: usize) -> Result<Vec<WrappingProbability>, IsingError> {
+        wrapping_probabilities(self, params, kind, temperatures, Some(n_threads))
+    }
+
+    // TODO: implement the following
+    // (doc) Monte Carlo estimation for temporal correlations after system is settled
+    // (doc) Returns the estaimate and uncertainty 1 sigma
+    // fn sample_temporal_correlations(&self) -> (f64 , f64);
+}
+
+/// Runs in turn on the calling thread
+const SERIAL: Option<usize> = None;
+
+/// Runs `per_run` once per run, in turn on the lattice itself or, given
+/// a number of `threads`, on that many workers with a clone of the
+/// lattice per worker. The seed of each run is drawn from the lattice's
+/// rng up front, so that both give the same results. Zero threads are
+/// an error.
+fn for_each_run<T, F>(lattice: &mut Lattice2d, params: &MonteCarloParams, threads: Option<usize>, per_run: F) -> Result<Vec<T>, IsingError>
+where
+    T: Send,
+    F: Fn(&mut Lattice2d, u64) -> T + Sync,
+{
+    if let Some(n_threads) = threads {
+        pool::check_n_threads(n_threads)?;
+    }
+    let seeds = run_seeds(lattice, params);
+    let Some(n_threads) = threads else {
+        // the runs reseed the lattice, leave its rng as a parallel run would
+        let rng = lattice.rng.clone();
+        let results = seeds.into_iter().map(|seed| per_run(lattice, seed)).collect();
+        lattice.rng = rng;
+        return Ok(results);
+    };
+    let lattice = &*lattice;
+    pool::map_on(n_threads, seeds, || lattice.clone(), |lattice_copy, seed| per_run(lattice_copy, seed))
+}
+
+/// The seed of each of the params.n_runs runs, drawn from the lattice's rng
+pub(crate) fn run_seeds(lattice: &mut Lattice2d, params: &MonteCarloParams) -> Vec<u64> {
+    (0..params.n_runs).map(|_| lattice.rng.gen()).collect()
+}
+
+/// Unwraps the result of serial runs, which happen on the calling thread
+/// and can only fail by panicking there
+fn serial<T>(result: Result<T, IsingError>) -> T {
+    result.expect("serial runs don't fail")
+}
+
+/// A single run: reseeds the lattice, resets its spins, lets it
+/// equilibrate and calls `sample` on each of the params.samples_per_run
+/// samples
+fn run<F>(lattice: &mut Lattice2d, params: &MonteCarloParams, seed: u64, sample: F)
+where
+    F: FnMut(&Lattice2d),
+{
+    start_run(lattice, params, seed);
+    continue_run(lattice, params, params.samples_per_run, sample);
+}
+
+/// The start of a run: reseeds the lattice, resets its spins and lets
+/// it equilibrate
+pub(crate) fn start_run(lattice: &mut Lattice2d, params: &MonteCarloParams, seed: u64) {
+    lattice.seed(seed);
+    lattice.reset_spins();
+    // Time evolve the system to cool (or heat) it
+    lattice.update_n(params.flips_to_skip);
+}
+
+/// Takes the next `n_samples` samples of a started run
+pub(crate) fn continue_run<F>(lattice: &mut Lattice2d, params: &MonteCarloParams, n_samples: usize, mut sample: F)
+where
+    F: FnMut(&Lattice2d),
+{
+    for _ in 0..n_samples {
+        // Time evolve the system a bit
+        lattice.update_n(params.flips_to_skip_between_samples);
+        sample(lattice);
+    }
+}
+
+/// Every sample of `measure`, one vec per run
+fn sample_series<T, F>(lattice: &mut Lattice2d, params: &MonteCarloParams, threads: Option<usize>, measure: F) -> Result<Vec<Vec<T>>, IsingError>
+where
+    T: Send,
+    F: Fn(&Lattice2d) -> T + Sync,
+{
+    for_each_run(lattice, params, threads, |lattice, seed| {
+        let mut samples = Vec::with_capacity(params.samples_per_run);
+        run(lattice, params, seed, |lattice| samples.push(measure(lattice)));
+        samples
+    })
+}
+
+/// One value per observable
+fn measure_all(observables: &[&dyn Observable], lattice: &Lattice2d) -> Vec<f64> {
+    observables.iter().map(|observable| observable.measure(lattice)).collect()
+}
+
+/// An array valued quantity measured on each sample
+type ArrayMeasure = fn(&Lattice2d) -> Array2<f64>;
+
+fn add_sample(sum: &mut Array2<f64>, sample: Array2<f64>) {
+    if sum.is_empty() {
+        *sum = sample;
+    } else {
+        *sum += &sample;
+    }
+}
+
+/// The average of `measure` over the samples of each run
+fn sample_run_means(lattice: &mut Lattice2d, params: &MonteCarloParams, threads: Option<usize>, measure: ArrayMeasure) -> Result<Vec<Array2<f64>>, IsingError> {
+    // the estimates need at least one run and one sample per run
+    if params.n_runs == 0 {
+        return Err(IsingError::InvalidParameter { name: "n_runs", reason: String::from("must be positive") });
+    }
+    if params.samples_per_run == 0 {
+        return Err(IsingError::InvalidParameter { name: "samples_per_run", reason: String::from("must be positive") });
+    }
+    for_each_run(lattice, params, threads, |lattice, seed| {
+        let mut sum = Array2::zeros((0, 0)); // the shape comes with the first sample
+        run(lattice, params, seed, |lattice| add_sample(&mut sum, measure(lattice)));
+        sum / params.samples_per_run as f64
+    })
+}
+
+/// Monte Carlo estimate of a correlation function, in real space G(d)
+/// or in k-space S(k), with error bars (the standard error over runs,
+/// NaN for a single run)
+#[derive(Clone, Debug)]
+pub struct CorrelationEstimate {
+    pub mean: Array2<f64>,           // G(d0, d1) or S(k0, k1)
+    pub sem: Array2<f64>,            // standard error of the mean
+    pub radial_mean: Vec<f64>,       // G(r) or S(|k|)
+    pub radial_sem: Vec<f64>,        // standard error of the radial mean
+    pub run_means: Vec<Array2<f64>>, // the average of each run, e.g. for a jackknife
+}
+
+impl CorrelationEstimate {
+    /// Combines the averages of each run, `radial` bins them by length
+    fn from_run_means(run_means: Vec<Array2<f64>>, radial: fn(&Array2<f64>) -> Vec<f64>) -> Self {
+        let n = run_means.len() as f64;
+        let dim = run_means.first().map_or((0, 0), |g| g.dim());
+        let mut mean = Array2::zeros(dim);
+        for g in run_means.iter() {
+            mean += g;
+        }
+        mean /= n;
+        let mut var = Array2::zeros(dim);
+        for g in run_means.iter() {
+            var += &(g - &mean).mapv(|x| x * x);
         }
-        // fetch_handle.into_iter().map(|c| energy.push(c.join().unwrap()));
+        let sem = (var / (n - 1.0) / n).mapv(f64::sqrt);
 
-        for thread in fetch_handle.into_iter() {
-            energy.push(thread.join().unwrap());
+        let radial_runs: Vec<Vec<f64>> = run_means.iter().map(radial).collect();
+        let n_bins = radial_runs.first().map_or(0, |r| r.len());
+        let mut radial_mean = vec![0.0; n_bins];
+        let mut radial_sem = vec![0.0; n_bins];
+        for r in 0..n_bins {
+            let values: Vec<f64> = radial_runs.iter().map(|run| run[r]).collect();
+            let stats = Stats::of(&values);
+            radial_mean[r] = stats.mean;
+            radial_sem[r] = stats.sem;
         }
-        return energy;
+        CorrelationEstimate {
+            mean,
+            sem,
+            radial_mean,
+            radial_sem,
+            run_means,
+        }
+    }
+
+    /// The number of runs
+    pub fn n_runs(&self) -> usize {
+        self.run_means.len()
     }
+}
 
+/// [[S(0), S(k_min)]], with S(k_min) averaged over the lowest wavevectors
+fn susceptibilities(lattice: &Lattice2d) -> Array2<f64> {
+    let lowest = lowest_wavevectors(lattice.dims);
+    let s_k_min = lowest.iter().map(|&idx| lattice.measure_structure_factor_at(idx)).sum::<f64>() / lowest.len() as f64;
+    array![[lattice.measure_structure_factor_at([0, 0]), s_k_min]]
+}
 
+/// Finite-size second-moment correlation length
+///
+/// ```text
+/// ξ₂ = sqrt(χ(0) / χ(k_min) - 1) / (2 sin(k_min / 2))
+/// ```
+///
+/// where χ(k) = <S(k)> is the magnetic susceptibility at wavevector k and
+/// k_min = 2π / L the smallest nonzero wavevector, L the larger
+/// dimension. ξ₂ / L crosses at Tc for different L. ξ₂ is NaN where
+/// χ(0) < χ(k_min), i.e. deep in the disordered phase on a noisy estimate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CorrelationLength {
+    pub xi: f64,        // ξ₂ from all runs
+    pub error: f64,     // jackknife error over runs, NaN for a single run
+    pub l: usize,       // the system size L
+    pub k_min: f64,     // 2π / L
+    pub chi_0: f64,     // χ(0), averaged over runs
+    pub chi_k_min: f64, // χ(k_min), averaged over runs
+}
 
-    /// Monte Carlo estimate of nearest neighbor correlations
-    /// Returns a vec of mean samples, of length params.n_runs
-    fn sample_neighbor_correlations(&mut self, params: &MonteCarloParams) -> Vec<Vec<f64>> {
-        // initiate nearest-neigbour correlation vector (empty Vec)
-        let mut nn_corr = vec![vec![0.0; params.samples_per_run]; params.n_runs]; 
-        for i in 0..params.n_runs {
-            self.reset_spins();
-            // Time evolve the system to cool (or heat) it
-            self.update_n(params.flips_to_skip);
-            for j in 0..params.samples_per_run {
-                // Time evolve the system a bit
-                self.update_n(params.flips_to_skip_between_samples);
-                nn_corr[i][j] = self.get_dot_spin_neighbours() as f64 / self.n_sites as f64 / 4.0;
-                // dividing by 4.0 scales it between -1 and +1, since 4 neighbours
-            }
+impl CorrelationLength {
+    /// ξ₂ for susceptibilities χ(0) and χ(k_min)
+    pub fn xi(chi_0: f64, chi_k_min: f64, k_min: f64) -> f64 {
+        (chi_0 / chi_k_min - 1.0).sqrt() / (2.0 * (k_min / 2.0).sin())
+    }
+
+    /// ξ₂ from a sampled structure factor, using its run averages
+    pub fn from_structure_factor(estimate: &CorrelationEstimate) -> Self {
+        let (rows, cols) = estimate.mean.dim();
+        let lowest = lowest_wavevectors([rows, cols]);
+        let run_means: Vec<Array2<f64>> = estimate
+            .run_means
+            .iter()
+            .map(|s| {
+                let s_k_min = lowest.iter().map(|&idx| s[idx]).sum::<f64>() / lowest.len() as f64;
+                array![[s[[0, 0]], s_k_min]]
+            })
+            .collect();
+        CorrelationLength::from_run_means([rows, cols], &run_means)
+    }
+
+    /// From the run averages of [[S(0), S(k_min)]]
+    fn from_run_means(dims: [usize; 2], run_means: &[Array2<f64>]) -> Self {
+        let l = dims[0].max(dims[1]);
+        let k_min = wavevector([l, l], [0, 1])[1];
+        let n = run_means.len() as f64;
+        let chi_0_sum: f64 = run_means.iter().map(|chi| chi[[0, 0]]).sum();
+        let chi_k_sum: f64 = run_means.iter().map(|chi| chi[[0, 1]]).sum();
+        let (chi_0, chi_k_min) = (chi_0_sum / n, chi_k_sum / n);
+        // leave one run out at a time
+        let xi_jack: Vec<f64> = run_means
+            .iter()
+            .map(|chi| {
+                let chi_0 = (chi_0_sum - chi[[0, 0]]) / (n - 1.0);
+                let chi_k_min = (chi_k_sum - chi[[0, 1]]) / (n - 1.0);
+                CorrelationLength::xi(chi_0, chi_k_min, k_min)
+            })
+            .collect();
+        let error = if run_means.len() > 1 {
+            let jack_mean = xi_jack.iter().sum::<f64>() / n;
+            ((n - 1.0) / n * xi_jack.iter().map(|xi| (xi - jack_mean).powi(2)).sum::<f64>()).sqrt()
+        } else {
+            f64::NAN
+        };
+        CorrelationLength {
+            xi: CorrelationLength::xi(chi_0, chi_k_min, k_min),
+            error,
+            l,
+            k_min,
+            chi_0,
+            chi_k_min,
         }
-        nn_corr
     }
 
-    /// Monte Carlo estimate of nearest neighbor correlations
-    /// Returns a vec of mean samples, of length params.n_runs
-    fn sample_neighbor_correlations_parallel(&mut self, params: &MonteCarloParams) -> Vec<Vec<f64>> {
-        // initiate nearest neighbour correlation vector (empty Vec)
-        let mut nn_corr = vec![];
-        let mut fetch_handle = vec![];
-        for _ in 0..params.n_runs {
-            // Create a clone: inits new lattice with same input params
-            let mut lattice_copy = self.clone();
-            let flips_to_skip = params.flips_to_skip;
-            let flips_to_skip_between_samples = params.flips_to_skip_between_samples;
-            let samples_per_run = params.samples_per_run;
-            // Time evolve system to cool (or heat) it
-            fetch_handle.push(thread::spawn(move || -> Vec<f64> {
-                lattice_copy.update_n(flips_to_skip);
-                let mut nn_samples = vec![];
-                for _ in 0..samples_per_run {
-                    // Time evolve the system a bit
-                    lattice_copy.update_n(flips_to_skip_between_samples);
-                    nn_samples.push(lattice_copy.get_dot_spin_neighbours() as f64 / lattice_copy.n_sites as f64 / 4.0);
+    /// ξ₂ / L and its error
+    pub fn xi_over_l(&self) -> (f64, f64) {
+        (self.xi / self.l as f64, self.error / self.l as f64)
+    }
+}
+
+/// n_s and the largest cluster fraction of a single sample
+fn cluster_sizes(lattice: &Lattice2d, kind: ClusterKind) -> (Vec<f64>, f64) {
+    let clusters = lattice.measure_clusters(kind);
+    (clusters.size_distribution(), clusters.largest_fraction())
+}
+
+/// Monte Carlo estimate of the cluster size distribution, with the
+/// standard error over runs (NaN for a single run)
+#[derive(Clone, Debug)]
+pub struct ClusterSizes {
+    pub n_s: Vec<f64>,             // clusters of size s per site, averaged over samples
+    pub n_s_sem: Vec<f64>,         // standard error of n_s
+    pub largest_fraction: f64,     // mean fraction of sites in the largest cluster
+    pub largest_fraction_sem: f64, // standard error of the largest fraction
+}
+
+impl ClusterSizes {
+    /// Averages (n_s, largest fraction) of each sample, the distributions
+    /// are padded with zeros up to the largest size seen in any sample
+    fn from_samples(samples: &[Vec<(Vec<f64>, f64)>]) -> Self {
+        let len = samples.iter().flatten().map(|(n_s, _)| n_s.len()).max().unwrap_or(0);
+        let run_means: Vec<Vec<f64>> = samples
+            .iter()
+            .filter(|run| !run.is_empty())
+            .map(|run| {
+                let mut mean = vec![0.0; len];
+                for (n_s, _) in run.iter() {
+                    for (m, x) in mean.iter_mut().zip(n_s) {
+                        *m += x / run.len() as f64;
+                    }
                 }
-                nn_samples
-            }));
+                mean
+            })
+            .collect();
+        let (n_s, n_s_sem) = (0..len)
+            .map(|s| {
+                let values: Vec<f64> = run_means.iter().map(|run| run[s]).collect();
+                let stats = Stats::of(&values);
+                (stats.mean, stats.sem)
+            })
+            .unzip();
+        let largest: Vec<Vec<f64>> = samples
+            .iter()
+            .map(|run| run.iter().map(|&(_, largest)| largest).collect())
+            .collect();
+        let largest_fraction = Stats::pooled(&largest).mean;
+        let largest_fraction_sem = Stats::between_runs(&largest).sem;
+        ClusterSizes {
+            n_s,
+            n_s_sem,
+            largest_fraction,
+            largest_fraction_sem,
+        }
+    }
+}
+
+fn wrapping(lattice: &Lattice2d, kind: ClusterKind) -> Wrapping {
+    lattice.measure_clusters(kind).wrapping()
+}
+
+/// See `MonteCarlo::sample_wrapping_probability`
+fn wrapping_probabilities(
+    lattice: &mut Lattice2d,
+    params: &MonteCarloParams,
+    kind: ClusterKind,
+    temperatures: &[f64],
+    threads: Option<usize>,
+) -> Result<Vec<WrappingProbability>, IsingError> {
+    let beta = lattice.beta;
+    let estimates = temperatures
+        .iter()
+        .map(|&temperature| {
+            lattice.beta = 1.0 / temperature;
+            let samples = sample_series(lattice, params, threads, move |lattice: &Lattice2d| wrapping(lattice, kind))?;
+            Ok(WrappingProbability::from_samples(temperature, &samples))
+        })
+        .collect();
+    lattice.beta = beta;
+    estimates
+}
+
+/// Monte Carlo estimate of the probability that some cluster wraps
+/// around the lattice, with the standard error over runs (NaN for a
+/// single run)
+#[derive(Clone, Copy, Debug)]
+pub struct WrappingProbability {
+    pub temperature: f64,
+    pub horizontal: f64, // some cluster wraps horizontally
+    pub vertical: f64,   // some cluster wraps vertically
+    pub either: f64,     // some cluster wraps in at least one direction
+    pub both: f64,       // clusters wrap in both directions
+    pub horizontal_sem: f64,
+    pub vertical_sem: f64,
+    pub either_sem: f64,
+    pub both_sem: f64,
+}
+
+impl WrappingProbability {
+    fn from_samples(temperature: f64, samples: &[Vec<Wrapping>]) -> Self {
+        let probability = |wraps: fn(&Wrapping) -> bool| {
+            let indicators: Vec<Vec<f64>> = samples
+                .iter()
+                .map(|run| run.iter().map(|w| if wraps(w) { 1.0 } else { 0.0 }).collect())
+                .collect();
+            (Stats::pooled(&indicators).mean, Stats::between_runs(&indicators).sem)
+        };
+        let (horizontal, horizontal_sem) = probability(|w| w.horizontal);
+        let (vertical, vertical_sem) = probability(|w| w.vertical);
+        let (either, either_sem) = probability(Wrapping::either);
+        let (both, both_sem) = probability(Wrapping::both);
+        WrappingProbability {
+            temperature,
+            horizontal,
+            vertical,
+            either,
+            both,
+            horizontal_sem,
+            vertical_sem,
+            either_sem,
+            both_sem,
         }
-        for thread in fetch_handle.into_iter() {
-            nn_corr.push(thread.join().unwrap());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::observable::FnObservable;
+
+    /// Workers of the parallel samplers in the tests
+    const THREADS: usize = 4;
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_params_serde_roundtrip() {
+        let params = MonteCarloParams {
+            n_runs: 3,
+            flips_to_skip: 1_000,
+            samples_per_run: 7,
+            flips_to_skip_between_samples: 50,
+        };
+        let json = serde_json::to_string(&params).unwrap();
+        let copy: MonteCarloParams = serde_json::from_str(&json).unwrap();
+        assert_eq!(copy.n_runs, 3);
+        assert_eq!(copy.samples_per_run, 7);
+        assert_eq!(copy.flips_to_skip_between_samples, 50);
+    }
+
+    #[test]
+    fn test_sample_correlation_function() {
+        let params = MonteCarloParams {
+            n_runs: 4,
+            flips_to_skip: 2_000,
+            samples_per_run: 5,
+            flips_to_skip_between_samples: 100,
+        };
+        let mut lattice = Lattice2d::builder().dims([8, 12]).beta(0.3).seed(3).build().unwrap();
+        let estimate = lattice.sample_correlation_function(&params).unwrap();
+        assert_eq!(estimate.mean.dim(), (8, 12));
+        assert_eq!(estimate.radial_mean.len(), 5);
+        assert_eq!(estimate.n_runs(), 4);
+        // G(0) = 1 - m^2 is at most 1, and correlations decay with distance
+        assert!(estimate.mean[[0, 0]] <= 1.0 && estimate.mean[[0, 0]] > 0.5);
+        assert!(estimate.radial_mean[0] > estimate.radial_mean[1]);
+        assert!(estimate.radial_mean[1] > estimate.radial_mean[4]);
+        assert!(estimate.sem.iter().all(|&x| x.is_finite() && x >= 0.0));
+
+        let estimate = lattice.sample_correlation_function_parallel(&params, THREADS).unwrap();
+        assert_eq!(estimate.radial_sem.len(), 5);
+        assert_eq!(estimate.n_runs(), 4);
+    }
+
+    #[test]
+    fn test_sample_structure_factor() {
+        let params = MonteCarloParams {
+            n_runs: 3,
+            flips_to_skip: 5_000,
+            samples_per_run: 4,
+            flips_to_skip_between_samples: 200,
+        };
+        // cold, ordered: the weight is at k = 0, S(0) = N m^2 ~ N
+        let mut lattice = Lattice2d::builder()
+            .dims([8, 8])
+            .init_type(InitType::AllUp)
+            .beta(2.0)
+            .seed(1)
+            .build()
+            .unwrap();
+        let estimate = lattice.sample_structure_factor(&params).unwrap();
+        assert_eq!(estimate.mean.dim(), (8, 8));
+        assert_eq!(estimate.radial_mean.len(), 5);
+        assert!(estimate.mean[[0, 0]] > 60.0);
+        assert!(estimate.radial_mean[1] < 1.0);
+
+        let estimate = lattice.sample_structure_factor_parallel(&params, THREADS).unwrap();
+        assert_eq!(estimate.run_means.len(), 3);
+        assert!(estimate.radial_mean[0] > 60.0);
+    }
+
+    #[test]
+    fn test_sample_quantity() {
+        let params = MonteCarloParams {
+            n_runs: 3,
+            flips_to_skip: 5_000,
+            samples_per_run: 4,
+            flips_to_skip_between_samples: 100,
+        };
+        // a cold antiferromagnet orders in a checkerboard
+        let mut lattice = Lattice2d::builder().dims([8, 8]).beta(2.0).seed(2).build().unwrap();
+        lattice.j = -1.0;
+        let samples = lattice.sample_quantity(Quantity::AbsStaggeredMagnetization, &params).samples;
+        assert_eq!(samples.len(), 3);
+        assert!(samples.iter().all(|run| run.len() == 4));
+        assert!(samples.iter().flatten().all(|&m| m > 0.8));
+        let samples = lattice.sample_quantity(Quantity::MagnetizationSquared, &params).samples;
+        assert!(samples.iter().flatten().all(|&m2| m2 < 0.1));
+
+        // the sublattices point in opposite directions
+        let [even, odd] = lattice.sample_sublattice_magnetizations(&params);
+        assert_eq!((even.name.as_str(), odd.name.as_str()), ("sublattice_even", "sublattice_odd"));
+        let products: Vec<f64> = even.samples.iter().flatten().zip(odd.samples.iter().flatten()).map(|(e, o)| e * o).collect();
+        assert_eq!(products.len(), 12);
+        assert!(products.iter().all(|&p| p < -0.5));
+        let blocks = lattice.sample_block_magnetizations(&params, 4);
+        assert!(blocks.samples.iter().flatten().all(|blocks| blocks.dim() == (2, 2)));
+        assert_eq!(blocks.mean().dim(), (2, 2));
+        assert_eq!(blocks.block_samples(1, 0).samples[2][3], blocks.samples[2][3][[1, 0]]);
+
+        let samples = lattice.sample_quantity_parallel(Quantity::MagnetizationQuartic, &params, THREADS).unwrap().samples;
+        assert_eq!(samples.len(), 3);
+        assert!(samples.iter().flatten().all(|&m4| (0.0..=1.0).contains(&m4)));
+        let [even, _] = lattice.sample_sublattice_magnetizations_parallel(&params, THREADS).unwrap();
+        assert!(even.samples.iter().all(|run| run.len() == 4));
+        let blocks = lattice.sample_block_magnetizations_parallel(&params, 3, THREADS).unwrap();
+        assert!(blocks.samples.iter().flatten().all(|blocks| blocks.dim() == (3, 3)));
+        assert_eq!(blocks.n_blocks(), [3, 3]);
+    }
+
+    #[test]
+    fn test_sample_cluster_sizes() {
+        let params = MonteCarloParams {
+            n_runs: 3,
+            flips_to_skip: 2_000,
+            samples_per_run: 4,
+            flips_to_skip_between_samples: 100,
+        };
+        let mut lattice = Lattice2d::builder().dims([12, 12]).beta(0.2).seed(9).build().unwrap();
+        for kind in [ClusterKind::Geometric, ClusterKind::FortuinKasteleyn] {
+            let sizes = lattice.sample_cluster_sizes(&params, kind);
+            // every site is in some cluster
+            let occupied: f64 = sizes.n_s.iter().enumerate().map(|(s, n)| s as f64 * n).sum();
+            assert!((occupied - 1.0).abs() < 1e-9);
+            assert_eq!(sizes.n_s.len(), sizes.n_s_sem.len());
+            assert!(sizes.largest_fraction > 0.0 && sizes.largest_fraction < 1.0);
+            assert!(sizes.largest_fraction_sem.is_finite());
         }
-        return nn_corr;
-    }
-
-
-    fn sample_magnetization_parallel(&mut self, params: &MonteCarloParams) -> Vec<Vec<f64>> {
-        let mut fetch_handle = vec![];
-        for _ in 0..params.n_runs {
-            // Create a clone: inits a new lattice with same input params
-            let mut lattice_copy = self.clone();
-            let flips_to_skip = params.flips_to_skip;
-            let flips_to_skip_between_samples = params.flips_to_skip_between_samples;
-            let samples_per_run = params.samples_per_run;
-            // Time evolve system to cool (or heat) it
-            fetch_handle.push(thread::spawn(move || -> Vec<f64> {
-                lattice_copy.update_n(flips_to_skip);
-                let mut mag_samples = vec![];
-                for _ in 0..samples_per_run {
-                    // Time evolve the system a bit
-                    lattice_copy.update_n(flips_to_skip_between_samples);
-                    mag_samples.push(lattice_copy.get_dot_spin_neighbours() as f64 / lattice_copy.n_sites as f64 / 4.0);
-                }
-                mag_samples
-            }));
+        // hot, the FK bonds are rare and most clusters are single sites
+        let fk = lattice.sample_cluster_sizes_parallel(&params, ClusterKind::FortuinKasteleyn, THREADS).unwrap();
+        let geometric = lattice.sample_cluster_sizes_parallel(&params, ClusterKind::Geometric, THREADS).unwrap();
+        assert!(fk.n_s[1] > geometric.n_s[1]);
+        assert!(fk.largest_fraction < geometric.largest_fraction);
+    }
+
+    #[test]
+    fn test_sample_wrapping_probability() {
+        let params = MonteCarloParams {
+            n_runs: 3,
+            flips_to_skip: 3_000,
+            samples_per_run: 5,
+            flips_to_skip_between_samples: 100,
+        };
+        let mut lattice = Lattice2d::builder()
+            .dims([12, 12])
+            .init_type(InitType::AllUp)
+            .beta(0.5)
+            .seed(2)
+            .build()
+            .unwrap();
+        let temperatures = [0.5, 20.0];
+        let estimates = lattice.sample_wrapping_probability(&params, ClusterKind::FortuinKasteleyn, &temperatures);
+        assert_eq!(lattice.beta, 0.5);
+        assert_eq!(estimates.len(), 2);
+        // cold, the ordered FK cluster spans the torus
+        assert_eq!(estimates[0].temperature, 0.5);
+        assert_eq!(estimates[0].both, 1.0);
+        assert_eq!(estimates[0].both_sem, 0.0);
+        // hot, the bonds are too sparse to percolate
+        assert_eq!(estimates[1].either, 0.0);
+        for estimate in estimates.iter() {
+            assert!(estimate.both <= estimate.horizontal && estimate.horizontal <= estimate.either);
         }
-        let mut magnetization = vec![];
-        for thread in fetch_handle.into_iter() {
-            magnetization.push(thread.join().unwrap());
+
+        let estimates = lattice.sample_wrapping_probability_parallel(&params, ClusterKind::Geometric, &temperatures, THREADS).unwrap();
+        assert_eq!(estimates[0].either, 1.0);
+        assert!(estimates[1].vertical <= estimates[1].either);
+    }
+
+    #[test]
+    fn test_sample_interface_width() {
+        let params = MonteCarloParams {
+            n_runs: 2,
+            flips_to_skip: 5_000,
+            samples_per_run: 3,
+            flips_to_skip_between_samples: 200,
+        };
+        // cold, the interface forced by the boundary stays and is narrow
+        let mut lattice = Lattice2d::builder().dims([12, 16]).beta(1.0).seed(8).build().unwrap();
+        lattice.set_boundary(Boundary::Interface);
+        let walls = lattice.sample_quantity(Quantity::DomainWallLength, &params).samples;
+        assert!(walls.iter().flatten().all(|&length| length >= 16.0));
+        let widths = lattice.sample_quantity(Quantity::InterfaceWidth, &params).samples;
+        assert!(widths.iter().flatten().all(|&w| w < 3.0));
+        assert!(lattice.nodes.row(0).iter().all(|&s| s == 1));
+        // the clones keep the boundary too
+        let walls = lattice.sample_quantity_parallel(Quantity::DomainWallLength, &params, THREADS).unwrap().samples;
+        assert!(walls.iter().flatten().all(|&length| length >= 16.0));
+    }
+
+    #[test]
+    fn test_sample_observables() {
+        let params = MonteCarloParams {
+            n_runs: 3,
+            flips_to_skip: 500,
+            samples_per_run: 6,
+            flips_to_skip_between_samples: 50,
+        };
+        let up_fraction = FnObservable::new("up_fraction", |lattice: &Lattice2d| {
+            lattice.nodes.iter().filter(|&&s| s == 1).count() as f64 / lattice.n_sites as f64
+        });
+        let observables: [&dyn Observable; 3] = [&Quantity::Magnetization, &Quantity::AbsMagnetization, &up_fraction];
+        let mut lattice = Lattice2d::builder().dims([8, 8]).beta(0.3).seed(6).build().unwrap();
+        for series in [
+            lattice.sample_observables(&observables, &params),
+            lattice.sample_observables_parallel(&observables, &params, THREADS).unwrap(),
+        ] {
+            let names: Vec<&str> = series.iter().map(|s| s.name.as_str()).collect();
+            assert_eq!(names, ["magnetization", "abs_magnetization", "up_fraction"]);
+            assert!(series.iter().all(|s| s.samples.len() == 3 && s.samples[0].len() == 6));
+            // all observables see the same configurations
+            let m = series[0].samples.iter().flatten();
+            let abs_m = series[1].samples.iter().flatten();
+            let up = series[2].samples.iter().flatten();
+            for ((m, abs_m), up) in m.zip(abs_m).zip(up) {
+                assert_eq!(m.abs(), *abs_m);
+                assert!((2.0 * up - 1.0 - m).abs() < 1e-12);
+            }
         }
-        return magnetization;
     }
 
-    /// Monte Carlo sample the magnetization
-    /// Returns a vec of mean samples, of length params.n_runs
-    fn sample_magnetization(&mut self, params: &MonteCarloParams) -> Vec<Vec<f64>> {
-        let mut mag_samples = vec![vec![0.0; params.samples_per_run]; params.n_runs];
-        // Instead, init new lattice object and use multithreding for parallel processing
-        for i in 0..params.n_runs {
-            self.reset_spins();
-            // Time evolve the system to cool (or heat) it
-            self.update_n(params.flips_to_skip);
-            for j in 0..params.samples_per_run {
-                // Time evolve the system a bit
-                self.update_n(params.flips_to_skip_between_samples);
-                mag_samples[i][j] = self.get_spin_mean();
+    #[test]
+    fn test_serial_parallel_equivalence() {
+        let params = MonteCarloParams {
+            n_runs: 3,
+            flips_to_skip: 300,
+            samples_per_run: 4,
+            flips_to_skip_between_samples: 40,
+        };
+        let build = || Lattice2d::builder().dims([8, 8]).beta(0.4).h(0.1).seed(21).build().unwrap();
+
+        for quantity in Quantity::ALL {
+            let serial = build().sample_quantity(quantity, &params).samples;
+            assert_eq!(serial, build().sample_quantity_parallel(quantity, &params, THREADS).unwrap().samples, "{:?}", quantity);
+        }
+        let observables: Vec<&dyn Observable> = Quantity::ALL.iter().map(|q| q as &dyn Observable).collect();
+        let serial = build().sample_observables(&observables, &params);
+        assert_eq!(serial, build().sample_observables_parallel(&observables, &params, THREADS).unwrap());
+        assert_eq!(serial, build().sample_observables_parallel(&observables, &params, 3).unwrap());
+        assert!(build().sample_observables_parallel(&observables, &params, 0).is_err());
+
+        let magnetization = build().sample_quantity(Quantity::Magnetization, &params).samples;
+        assert_eq!(build().sample_magnetization(&params).samples, magnetization);
+        assert_eq!(build().sample_magnetization_parallel(&params, THREADS).unwrap().samples, magnetization);
+        assert_eq!(build().sample_energy(&params).samples, build().sample_energy_parallel(&params, THREADS).unwrap().samples);
+        assert_eq!(
+            build().sample_neighbor_correlations(&params).samples,
+            build().sample_neighbor_correlations_parallel(&params, THREADS).unwrap().samples
+        );
+
+        assert_eq!(
+            build().sample_sublattice_magnetizations(&params),
+            build().sample_sublattice_magnetizations_parallel(&params, THREADS).unwrap()
+        );
+        assert_eq!(
+            build().sample_block_magnetizations(&params, 4),
+            build().sample_block_magnetizations_parallel(&params, 4, THREADS).unwrap()
+        );
+        assert_eq!(
+            build().sample_correlation_function(&params).unwrap().run_means,
+            build().sample_correlation_function_parallel(&params, THREADS).unwrap().run_means
+        );
+        assert_eq!(
+            build().sample_structure_factor(&params).unwrap().run_means,
+            build().sample_structure_factor_parallel(&params, THREADS).unwrap().run_means
+        );
+        assert_eq!(build().sample_correlation_length(&params).unwrap(), build().sample_correlation_length_parallel(&params, THREADS).unwrap());
+        for kind in [ClusterKind::Geometric, ClusterKind::FortuinKasteleyn] {
+            let serial = build().sample_cluster_sizes(&params, kind);
+            let parallel = build().sample_cluster_sizes_parallel(&params, kind, THREADS).unwrap();
+            assert_eq!(serial.n_s, parallel.n_s);
+            assert_eq!(serial.largest_fraction, parallel.largest_fraction);
+            let serial = build().sample_wrapping_probability(&params, kind, &[1.5, 3.0]);
+            let parallel = build().sample_wrapping_probability_parallel(&params, kind, &[1.5, 3.0], THREADS).unwrap();
+            for (s, p) in serial.iter().zip(&parallel) {
+                assert_eq!([s.horizontal, s.vertical, s.either, s.both], [p.horizontal, p.vertical, p.either, p.both]);
             }
         }
-        mag_samples
     }
 
+    #[test]
+    fn test_worker_panic_is_an_error() {
+        let params = MonteCarloParams {
+            n_runs: 4,
+            flips_to_skip: 10,
+            samples_per_run: 2,
+            flips_to_skip_between_samples: 10,
+        };
+        let broken = FnObservable::new("broken", |_: &Lattice2d| -> f64 { panic!("cannot measure") });
+        let mut lattice = Lattice2d::builder().dims([4, 4]).seed(1).build().unwrap();
+        match lattice.sample_observables_parallel(&[&Quantity::Energy, &broken], &params, THREADS) {
+            Err(IsingError::WorkerPanicked(msg)) => assert_eq!(msg, "cannot measure"),
+            other => panic!("expected a worker panic, got {:?}", other),
+        }
+    }
 
-    // TODO: implement the following
-    // (doc) Monte Carlo estimation for spacial correlations after system is settled
-    // (doc) Returns the estimate and uncertainty 1 sigma
-    // fn sample_spacial_correlations(&self , params:MonteCarloParams) -> [f64;3] {
-    //     // initiate nearest neighbo
-    // }
-    // TODO: implement the following
-    // (doc) Monte Carlo estimation for temporal correlations after system is settled
-    // (doc) Returns the estaimate and uncertainty 1 sigma
-    // fn sample_temporal_correlations(&self) -> (f64 , f64);
-    // TODO: implement the following
-    // (doc) Monte Carlo estimagion of all metrics
-    // (doc) Returns a Vec (or dict?) of all the metrics
-    // fn sample_estimate_all_metrics(&self , params:MonteCarloParams) -> Vec;
-}
+    #[test]
+    fn test_correlation_length_jackknife() {
+        // ξ₂ = sqrt(χ(0)/χ(k_min) - 1) / (2 sin(π/L)), with L = 8
+        let runs = [array![[10.0, 2.0]], array![[12.0, 2.0]], array![[14.0, 2.0]]];
+        let xi = CorrelationLength::from_run_means([8, 8], &runs);
+        let denom = 2.0 * (std::f64::consts::PI / 8.0).sin();
+        assert_eq!(xi.chi_0, 12.0);
+        assert!((xi.xi - 5.0f64.sqrt() / denom).abs() < 1e-12);
+        // by hand: leave-one-out χ(0) = 13, 12, 11
+        let jack: Vec<f64> = [13.0f64, 12.0, 11.0].iter().map(|c| (c / 2.0 - 1.0).sqrt() / denom).collect();
+        let mean = jack.iter().sum::<f64>() / 3.0;
+        let error = (2.0 / 3.0 * jack.iter().map(|x| (x - mean).powi(2)).sum::<f64>()).sqrt();
+        assert!((xi.error - error).abs() < 1e-12);
+        assert!((xi.xi_over_l().0 - xi.xi / 8.0).abs() < 1e-12);
+        assert!(CorrelationLength::from_run_means([8, 8], &runs[..1]).error.is_nan());
+    }
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    #[test]
+    fn test_correlation_estimates_need_samples() {
+        let mut lattice = Lattice2d::builder().dims([4, 4]).seed(1).build().unwrap();
+        let no_runs = MonteCarloParams {
+            n_runs: 0,
+            flips_to_skip: 10,
+            samples_per_run: 2,
+            flips_to_skip_between_samples: 10,
+        };
+        let no_samples = MonteCarloParams {
+            n_runs: 2,
+            samples_per_run: 0,
+            ..no_runs
+        };
+        for (params, param) in [(&no_runs, "n_runs"), (&no_samples, "samples_per_run")] {
+            for result in [
+                lattice.sample_correlation_length(params).map(|_| ()),
+                lattice.sample_correlation_length_parallel(params, THREADS).map(|_| ()),
+                lattice.sample_correlation_function(params).map(|_| ()),
+                lattice.sample_structure_factor_parallel(params, THREADS).map(|_| ()),
+            ] {
+                assert!(matches!(result, Err(IsingError::InvalidParameter { name, .. }) if name == param));
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample_correlation_length() {
+        let params = MonteCarloParams {
+            n_runs: 4,
+            flips_to_skip: 20_000,
+            samples_per_run: 20,
+            flips_to_skip_between_samples: 200,
+        };
+        // ξ grows towards Tc
+        let mut hot = Lattice2d::builder().dims([12, 12]).beta(0.33).seed(3).build().unwrap();
+        let mut warm = Lattice2d::builder().dims([12, 12]).beta(0.4).seed(1).build().unwrap();
+        let xi_hot = hot.sample_correlation_length(&params).unwrap();
+        let xi_warm = warm.sample_correlation_length_parallel(&params, THREADS).unwrap();
+        assert_eq!(xi_hot.l, 12);
+        assert!(xi_hot.error.is_finite());
+        assert!(xi_warm.xi > xi_hot.xi);
+
+        // the same estimate from a sampled structure factor
+        let estimate = hot.sample_structure_factor(&params).unwrap();
+        let xi = CorrelationLength::from_structure_factor(&estimate);
+        assert!((xi.chi_0 - estimate.mean[[0, 0]]).abs() < 1e-9);
+        assert_eq!(xi.k_min, xi_hot.k_min);
+    }
 
     #[test]
     fn test_sample_energy() {
@@ -247,7 +1029,7 @@ mod test {
             0.0f64, // h static field term
             beta,   // 1/TkB
         );
-        let energy_samples: Vec<Vec<f64>> = lattice.sample_energy(&params);
+        let energy_samples: Vec<Vec<f64>> = lattice.sample_energy(&params).samples;
         assert_eq!(energy_samples.len(), params.n_runs);
         assert_eq!(energy_samples[0].len(), params.samples_per_run);
     }
@@ -270,7 +1052,7 @@ mod test {
             0.0f64, // h static field term
             beta,   // 1/TkB
         );
-        let energy_samples: Vec<Vec<f64>> = lattice.sample_energy_parallel(&params);
+        let energy_samples: Vec<Vec<f64>> = lattice.sample_energy_parallel(&params, THREADS).unwrap().samples;
         assert_eq!(energy_samples.len(), params.n_runs);
         assert_eq!(energy_samples[0].len(), params.samples_per_run);
     }
@@ -294,7 +1076,7 @@ mod test {
             0.1f64, // h static field term
             beta,   // 1/TkB
         );
-        let nn_corr: Vec<Vec<f64>> = lattice.sample_neighbor_correlations(&params);
+        let nn_corr: Vec<Vec<f64>> = lattice.sample_neighbor_correlations(&params).samples;
         assert_eq!(nn_corr.len(), params.n_runs);
         assert_eq!(nn_corr[0].len(), params.samples_per_run);
     }
@@ -317,7 +1099,7 @@ mod test {
             0.0f64, // h static field term
             beta,   // 1/TkB
         );
-        let nn_corr: Vec<Vec<f64>> = lattice.sample_neighbor_correlations_parallel(&params);
+        let nn_corr: Vec<Vec<f64>> = lattice.sample_neighbor_correlations_parallel(&params, THREADS).unwrap().samples;
         assert_eq!(nn_corr.len(), params.n_runs);
         assert_eq!(nn_corr[0].len(), params.samples_per_run);
     }
@@ -0,0 +1,149 @@
+//! Quantities measured on each Monte Carlo sample. An `Observable` maps a
+//! configuration to a number, `MonteCarlo::sample_observables` measures
+//! any set of them in a single pass over the samples and returns a
+//! `SampleSet` for each.
+//!
+//! ```
+//! use ising_lib::lattice2d::Lattice2d;
+//! use ising_lib::monte_carlo_measurement::{MonteCarlo, MonteCarloParams};
+//! use ising_lib::observable::{FnObservable, Observable, Quantity};
+//!
+//! // the fraction of up spins in the top row
+//! let top_row = FnObservable::new("top_row", |lattice: &Lattice2d| {
+//!     lattice.nodes.row(0).iter().filter(|&&s| s == 1).count() as f64 / lattice.dims[1] as f64
+//! });
+//! let params = MonteCarloParams {
+//!     n_runs: 2,
+//!     flips_to_skip: 100,
+//!     samples_per_run: 3,
+//!     flips_to_skip_between_samples: 10,
+//! };
+//! let mut lattice = Lattice2d::builder().dims([8, 8]).build().unwrap();
+//! let series = lattice.sample_observables(&[&Quantity::Energy, &top_row], &params);
+//! assert_eq!(series[1].name, "top_row");
+//! assert_eq!(series[1].samples.len(), 2); // runs
+//! assert_eq!(series[1].samples[0].len(), 3); // samples per run
+//! ```
+
+use crate::lattice2d::Lattice2d;
+use crate::measurement::Measurement;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A scalar quantity measured on a configuration of the system S, e.g. a
+/// `Lattice2d` or a `Graph`. Observables are shared between the threads
+/// of the parallel samplers, hence `Sync`.
+pub trait Observable<S = Lattice2d>: Sync {
+    /// Names the time series, e.g. in a table header
+    fn name(&self) -> &str;
+    fn measure(&self, system: &S) -> f64;
+}
+
+/// A built-in scalar quantity measured on each sample, see `Observable`,
+/// e.g. by a checkpointed run or `MonteCarlo::sample_quantity`
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Quantity {
+    Energy,
+    NeighborCorrelation,
+    Magnetization,
+    AbsMagnetization,
+    MagnetizationSquared,
+    MagnetizationQuartic,
+    StaggeredMagnetization,
+    AbsStaggeredMagnetization,
+    DomainWallLength,
+    InterfaceWidth,
+}
+
+impl Quantity {
+    /// Every built-in quantity
+    pub const ALL: [Quantity; 10] = [
+        Quantity::Energy,
+        Quantity::NeighborCorrelation,
+        Quantity::Magnetization,
+        Quantity::AbsMagnetization,
+        Quantity::MagnetizationSquared,
+        Quantity::MagnetizationQuartic,
+        Quantity::StaggeredMagnetization,
+        Quantity::AbsStaggeredMagnetization,
+        Quantity::DomainWallLength,
+        Quantity::InterfaceWidth,
+    ];
+}
+
+/// The built-in quantities, measured the same way as by the
+/// corresponding `MonteCarlo` samplers
+impl Observable for Quantity {
+    fn name(&self) -> &str {
+        match self {
+            Quantity::Energy => "energy",
+            Quantity::NeighborCorrelation => "nn_correlation",
+            Quantity::Magnetization => "magnetization",
+            Quantity::AbsMagnetization => "abs_magnetization",
+            Quantity::MagnetizationSquared => "magnetization_squared",
+            Quantity::MagnetizationQuartic => "magnetization_quartic",
+            Quantity::StaggeredMagnetization => "staggered_magnetization",
+            Quantity::AbsStaggeredMagnetization => "abs_staggered_magnetization",
+            Quantity::DomainWallLength => "domain_wall_length",
+            Quantity::InterfaceWidth => "interface_width",
+        }
+    }
+
+    fn measure(&self, lattice: &Lattice2d) -> f64 {
+        match self {
+            Quantity::Energy => lattice.measure_energy(),
+            Quantity::NeighborCorrelation => {
+                // dividing by 4.0 scales it between -1 and +1, since 4 neighbours
+                lattice.get_dot_spin_neighbours() as f64 / lattice.n_sites as f64 / 4.0
+            }
+            Quantity::Magnetization => lattice.get_spin_mean(),
+            Quantity::AbsMagnetization => lattice.measure_abs_magnetization(),
+            Quantity::MagnetizationSquared => lattice.measure_magnetization_squared(),
+            Quantity::MagnetizationQuartic => lattice.measure_magnetization_quartic(),
+            Quantity::StaggeredMagnetization => lattice.measure_staggered_magnetization(),
+            Quantity::AbsStaggeredMagnetization => lattice.measure_staggered_magnetization().abs(),
+            Quantity::DomainWallLength => lattice.measure_domain_wall_length() as f64,
+            Quantity::InterfaceWidth => lattice.measure_interface_width(),
+        }
+    }
+}
+
+/// An observable from a name and a function, for one-off measurements
+pub struct FnObservable<F> {
+    name: String,
+    f: F,
+}
+
+impl<F> FnObservable<F> {
+    pub fn new(name: &str, f: F) -> Self {
+        FnObservable { name: name.to_owned(), f }
+    }
+}
+
+impl<S, F: Fn(&S) -> f64 + Sync> Observable<S> for FnObservable<F> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn measure(&self, system: &S) -> f64 {
+        (self.f)(system)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lattice2d::InitType;
+
+    #[test]
+    fn test_observables() {
+        let lattice = Lattice2d::builder().dims([4, 4]).init_type(InitType::AllUp).build().unwrap();
+        assert_eq!(Quantity::Energy.measure(&lattice), -64.0); // each bond is counted twice
+        assert_eq!(Quantity::NeighborCorrelation.measure(&lattice), 1.0);
+        assert_eq!(Quantity::Magnetization.name(), "magnetization");
+        let double = FnObservable::new("double_m", |lattice: &Lattice2d| 2.0 * lattice.get_spin_mean());
+        assert_eq!(double.name(), "double_m");
+        assert_eq!(double.measure(&lattice), 2.0);
+    }
+}
@@ -0,0 +1,271 @@
+//! Writing tables of numbers, e.g. a time series of observables or the
+//! results of a temperature scan, as CSV, JSON Lines or NPY. Rows are
+//! written as they come, so long runs can be followed while they go.
+//!
+//! ```
+//! use ising_lib::output::{Format, TableWriter};
+//!
+//! let mut out = Vec::new();
+//! let mut table = TableWriter::new(&mut out, Format::Csv, &["sweep", "energy"], 2).unwrap();
+//! table.write_row(&[0.0, -1.5]).unwrap();
+//! table.write_row(&[1.0, -1.75]).unwrap();
+//! table.finish().unwrap();
+//! assert_eq!(String::from_utf8(out).unwrap(), "sweep,energy\n0,-1.5\n1,-1.75\n");
+//! ```
+
+use crate::error::IsingError;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Table file formats
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Csv,       // a header line with the column names, then one line per row
+    JsonLines, // one JSON object per row, keyed by the column names
+    Npy,       // a 2d little endian f64 NumPy array, without column names
+}
+
+impl Format {
+    /// The format of a file by its extension (`csv`, `jsonl` or `ndjson`,
+    /// `npy`)
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, IsingError> {
+        let path = path.as_ref();
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .ok_or_else(|| IsingError::Format(format!("unknown table format for {}", path.display())))?
+            .parse()
+    }
+}
+
+impl FromStr for Format {
+    type Err = IsingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Format::Csv),
+            "jsonl" | "ndjson" => Ok(Format::JsonLines),
+            "npy" => Ok(Format::Npy),
+            _ => Err(IsingError::Format(format!("unknown table format {}", s))),
+        }
+    }
+}
+
+/// One value of a row, see `TableWriter::write_cells`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cell<'a> {
+    Number(f64),
+    /// A name, e.g. of an observable, written as a string in CSV and JSON
+    /// Lines and as the number (e.g. its index) in NPY, which only holds
+    /// numbers
+    Label(&'a str, f64),
+}
+
+/// Writes the rows of a table with fixed columns
+pub struct TableWriter<W: Write> {
+    w: W,
+    format: Format,
+    columns: Vec<String>,
+    n_rows: usize, // promised in the NPY header
+    rows_written: usize,
+}
+
+impl<W: Write> TableWriter<W> {
+    /// Writes the header. The NPY header holds the shape of the array, so
+    /// the number of rows has to be known in advance, it is only checked
+    /// for the other formats.
+    pub fn new(mut w: W, format: Format, columns: &[&str], n_rows: usize) -> Result<Self, IsingError> {
+        match format {
+            Format::Csv => writeln!(w, "{}", columns.join(","))?,
+            Format::JsonLines => {}
+            Format::Npy => write_npy_header(&mut w, [n_rows, columns.len()])?,
+        }
+        Ok(TableWriter {
+            w,
+            format,
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            n_rows,
+            rows_written: 0,
+        })
+    }
+
+    /// Writes one row, which must have a value for every column
+    pub fn write_row(&mut self, row: &[f64]) -> Result<(), IsingError> {
+        let cells: Vec<Cell> = row.iter().map(|&x| Cell::Number(x)).collect();
+        self.write_cells(&cells)
+    }
+
+    /// Writes one row that may hold labels, which must have a value for
+    /// every column
+    pub fn write_cells(&mut self, row: &[Cell]) -> Result<(), IsingError> {
+        if row.len() != self.columns.len() {
+            return Err(IsingError::InvalidParameter {
+                name: "row",
+                reason: format!("has {} values but there are {} columns", row.len(), self.columns.len()),
+            });
+        }
+        if self.format == Format::Npy && self.rows_written == self.n_rows {
+            return Err(IsingError::InvalidParameter {
+                name: "row",
+                reason: format!("the NPY header promised {} rows", self.n_rows),
+            });
+        }
+        match self.format {
+            Format::Csv => {
+                let values: Vec<String> = row.iter().map(csv_cell).collect();
+                writeln!(self.w, "{}", values.join(","))?;
+            }
+            Format::JsonLines => {
+                let fields: Vec<String> = self
+                    .columns
+                    .iter()
+                    .zip(row)
+                    .map(|(column, cell)| format!("\"{}\":{}", column, json_cell(cell)))
+                    .collect();
+                writeln!(self.w, "{{{}}}", fields.join(","))?;
+            }
+            Format::Npy => {
+                for cell in row {
+                    let x = match *cell {
+                        Cell::Number(x) | Cell::Label(_, x) => x,
+                    };
+                    self.w.write_all(&x.to_le_bytes())?;
+                }
+            }
+        }
+        self.rows_written += 1;
+        Ok(())
+    }
+
+    /// Flushes and returns the writer, fails if fewer rows were written
+    /// than the NPY header promised
+    pub fn finish(mut self) -> Result<W, IsingError> {
+        self.w.flush()?;
+        if self.format == Format::Npy && self.rows_written != self.n_rows {
+            return Err(IsingError::InvalidParameter {
+                name: "n_rows",
+                reason: format!("{} rows were promised but {} written", self.n_rows, self.rows_written),
+            });
+        }
+        Ok(self.w)
+    }
+}
+
+/// Labels are quoted if they hold a separator or a quote
+fn csv_cell(cell: &Cell) -> String {
+    match *cell {
+        Cell::Number(x) => x.to_string(),
+        Cell::Label(name, _) if name.contains([',', '"', '\n']) => format!("\"{}\"", name.replace('"', "\"\"")),
+        Cell::Label(name, _) => name.to_owned(),
+    }
+}
+
+/// Labels are escaped strings; JSON has no infinities or NaN, they are
+/// written as null
+fn json_cell(cell: &Cell) -> String {
+    match *cell {
+        Cell::Number(x) if x.is_finite() => x.to_string(),
+        Cell::Number(_) => "null".to_owned(),
+        Cell::Label(name, _) => {
+            let mut json = String::from('"');
+            for c in name.chars() {
+                match c {
+                    '"' | '\\' => json.extend(['\\', c]),
+                    c if c.is_control() => json += &format!("\\u{:04x}", c as u32),
+                    c => json.push(c),
+                }
+            }
+            json + "\""
+        }
+    }
+}
+
+/// Version 1.0 NPY header of a C-ordered f64 array, padded so that the
+/// data starts at a multiple of 64 bytes
+fn write_npy_header<W: Write>(w: &mut W, shape: [usize; 2]) -> Result<(), IsingError> {
+    let mut header = format!(
+        "{{'descr': '<f8', 'fortran_order': False, 'shape': ({}, {}), }}",
+        shape[0], shape[1]
+    );
+    // magic (6), version (2) and header length (2) come first
+    let unpadded = 10 + header.len() + 1;
+    header += &" ".repeat((64 - unpadded % 64) % 64);
+    header.push('\n');
+    let len = u16::try_from(header.len()).map_err(|_| IsingError::InvalidDims(shape.to_vec()))?;
+    w.write_all(b"\x93NUMPY\x01\x00")?;
+    w.write_all(&len.to_le_bytes())?;
+    w.write_all(header.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write_table(format: Format, rows: &[[f64; 2]]) -> Vec<u8> {
+        let mut table = TableWriter::new(Vec::new(), format, &["t", "m"], rows.len()).unwrap();
+        for row in rows {
+            table.write_row(row).unwrap();
+        }
+        table.finish().unwrap()
+    }
+
+    #[test]
+    fn test_csv_and_jsonl() {
+        let rows = [[2.0, 0.5], [2.25, f64::NAN]];
+        let csv = String::from_utf8(write_table(Format::Csv, &rows)).unwrap();
+        assert_eq!(csv, "t,m\n2,0.5\n2.25,NaN\n");
+        let jsonl = String::from_utf8(write_table(Format::JsonLines, &rows)).unwrap();
+        assert_eq!(jsonl, "{\"t\":2,\"m\":0.5}\n{\"t\":2.25,\"m\":null}\n");
+    }
+
+    #[test]
+    fn test_labels() {
+        let cells = [Cell::Label("energy", 0.0), Cell::Number(-1.5), Cell::Label("a \"b\", c", 1.0)];
+        let table = |format| {
+            let mut table = TableWriter::new(Vec::new(), format, &["observable", "mean", "note"], 1).unwrap();
+            table.write_cells(&cells).unwrap();
+            table.finish().unwrap()
+        };
+        let csv = String::from_utf8(table(Format::Csv)).unwrap();
+        assert_eq!(csv, "observable,mean,note\nenergy,-1.5,\"a \"\"b\"\", c\"\n");
+        let jsonl = String::from_utf8(table(Format::JsonLines)).unwrap();
+        assert_eq!(jsonl, "{\"observable\":\"energy\",\"mean\":-1.5,\"note\":\"a \\\"b\\\", c\"}\n");
+        let npy = table(Format::Npy);
+        let data: Vec<f64> = npy[npy.len() - 24..]
+            .chunks(8)
+            .map(|c| f64::from_le_bytes(c.try_into().unwrap()))
+            .collect();
+        assert_eq!(data, vec![0.0, -1.5, 1.0]);
+    }
+
+    #[test]
+    fn test_npy() {
+        let bytes = write_table(Format::Npy, &[[1.0, -1.0], [0.5, 3.0]]);
+        assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+        let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        let header = std::str::from_utf8(&bytes[10..10 + header_len]).unwrap();
+        assert!(header.contains("'shape': (2, 2)"));
+        assert!(header.ends_with('\n'));
+        let data: Vec<f64> = bytes[10 + header_len..]
+            .chunks(8)
+            .map(|c| f64::from_le_bytes(c.try_into().unwrap()))
+            .collect();
+        assert_eq!(data, vec![1.0, -1.0, 0.5, 3.0]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut table = TableWriter::new(Vec::new(), Format::Npy, &["x"], 1).unwrap();
+        assert!(table.write_row(&[1.0, 2.0]).is_err()); // too many values
+        assert!(table.finish().is_err()); // no rows written
+        let mut table = TableWriter::new(Vec::new(), Format::Npy, &["x"], 0).unwrap();
+        assert!(table.write_row(&[1.0]).is_err()); // more rows than promised
+
+        assert_eq!(Format::from_path("out/scan.NPY").unwrap(), Format::Npy);
+        assert_eq!("ndjson".parse::<Format>().unwrap(), Format::JsonLines);
+        assert!(Format::from_path("scan").is_err());
+        assert!("xlsx".parse::<Format>().is_err());
+    }
+}
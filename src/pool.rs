@@ -0,0 +1,144 @@
+//! The bounded pool of worker threads behind the parallel Monte Carlo
+//! samplers. Instead of a thread per run, at most `n_threads` workers
+//! take the runs one at a time as they become free, so that a few slow
+//! runs don't hold up the others and a thousand runs don't mean a
+//! thousand OS threads. Each `_parallel` sampler takes the number of
+//! workers, `default_n_threads` is one per CPU.
+
+use crate::error::IsingError;
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Rejects a pool without workers
+pub(crate) fn check_n_threads(n_threads: usize) -> Result<(), IsingError> {
+    if n_threads == 0 {
+        return Err(IsingError::InvalidParameter {
+            name: "n_threads",
+            reason: "must be at least 1".to_owned(),
+        });
+    }
+    Ok(())
+}
+
+/// The default number of worker threads of the parallel samplers, one
+/// per CPU
+pub fn default_n_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Calls `job` on each of the inputs on at most `n_threads` scoped
+/// workers and returns the results in the order of the inputs. Each
+/// worker makes its own state with `init`, e.g. a copy of the lattice,
+/// and takes the next input whenever it is done with one. If a job
+/// panics, no new jobs are started and the panic message is returned.
+pub(crate) fn map_on<I, S, T, Init, Job>(n_threads: usize, inputs: Vec<I>, init: Init, job: Job) -> Result<Vec<T>, IsingError>
+where
+    I: Send,
+    T: Send,
+    Init: Fn() -> S + Sync,
+    Job: Fn(&mut S, I) -> T + Sync,
+{
+    let n_jobs = inputs.len();
+    let inputs: Vec<Mutex<Option<I>>> = inputs.into_iter().map(|input| Mutex::new(Some(input))).collect();
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let worker = || -> Result<Vec<(usize, T)>, IsingError> {
+        let mut state = None;
+        let mut done = vec![];
+        while !failed.load(Ordering::Relaxed) {
+            let i = next.fetch_add(1, Ordering::Relaxed);
+            if i >= n_jobs {
+                break;
+            }
+            let input = inputs[i].lock().unwrap().take().unwrap();
+            match panic::catch_unwind(AssertUnwindSafe(|| job(state.get_or_insert_with(&init), input))) {
+                Ok(output) => done.push((i, output)),
+                Err(payload) => {
+                    failed.store(true, Ordering::Relaxed);
+                    return Err(IsingError::WorkerPanicked(panic_message(payload)));
+                }
+            }
+        }
+        Ok(done)
+    };
+
+    let results = thread::scope(|scope| {
+        let handles: Vec<_> = (0..n_threads.clamp(1, n_jobs.max(1))).map(|_| scope.spawn(worker)).collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap_or_else(|payload| Err(IsingError::WorkerPanicked(panic_message(payload)))))
+            .collect::<Vec<_>>()
+    });
+    let mut outputs: Vec<Option<T>> = (0..n_jobs).map(|_| None).collect();
+    for result in results {
+        for (i, output) in result? {
+            outputs[i] = Some(output);
+        }
+    }
+    Ok(outputs.into_iter().map(|output| output.unwrap()).collect())
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(msg) => *msg,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(msg) => (*msg).to_owned(),
+            Err(_) => "unknown panic".to_owned(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_map_on() {
+        // jobs of very different lengths, more jobs than workers
+        let running = AtomicUsize::new(0);
+        let most_running = AtomicUsize::new(0);
+        let inputs: Vec<u64> = (0..20).collect();
+        let outputs = map_on(
+            3,
+            inputs,
+            || 0,
+            |n_done: &mut usize, x| {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                most_running.fetch_max(now, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(x % 4));
+                running.fetch_sub(1, Ordering::SeqCst);
+                *n_done += 1;
+                x * x
+            },
+        )
+        .unwrap();
+        assert_eq!(outputs, (0..20).map(|x| x * x).collect::<Vec<u64>>());
+        assert!(most_running.load(Ordering::SeqCst) <= 3);
+        assert!(map_on(4, vec![], || (), |_, x: u8| x).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_worker_panic() {
+        let result = map_on(2, (0..10).collect(), || (), |_, x: i32| {
+            if x == 7 {
+                panic!("run {} failed", x);
+            }
+            x
+        });
+        match result {
+            Err(IsingError::WorkerPanicked(msg)) => assert_eq!(msg, "run 7 failed"),
+            other => panic!("expected a worker panic, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_n_threads() {
+        assert!(check_n_threads(0).is_err());
+        assert!(check_n_threads(1).is_ok());
+        assert!(default_n_threads() >= 1);
+    }
+}
@@ -0,0 +1,351 @@
+//! Drawing lattices in the terminal. `Lattice2d` implements `Display`
+//! as a plain `#`/space picture, and `TerminalRenderer` draws to any
+//! `io::Write` with ANSI colors, Unicode half-blocks (two rows of sites
+//! per character cell) and in-place redrawing of successive frames.
+//! `sparkline` plots a history of values on a single line.
+//!
+//! ```
+//! use ising_lib::lattice2d::Lattice2d;
+//! use ising_lib::render::{Overlay, TerminalRenderer};
+//!
+//! let mut lattice = Lattice2d::new_basic([20, 40]);
+//! let mut renderer = TerminalRenderer::new().in_place(true);
+//! let mut out = std::io::stdout();
+//! for _ in 0..3 {
+//!     lattice.update_n(1_000);
+//!     renderer.render(&lattice, &Overlay::Spins, &mut out).unwrap();
+//! }
+//! ```
+
+use crate::lattice2d::Lattice2d;
+use crate::measurement::Measurement;
+use ndarray::prelude::*;
+use std::fmt;
+use std::io::{self, Write};
+
+// 256-color palette indices
+const COLOR_UP: u8 = 214; // orange
+const COLOR_DOWN: u8 = 25; // blue
+const COLOR_VACANT: u8 = 240; // gray
+const COLOR_OTHER: u8 = 196; // red, not a spin 1/2 value
+const ENERGY_RAMP: [u8; 11] = [19, 25, 31, 37, 43, 149, 185, 215, 209, 203, 197]; // low to high
+const CLUSTER_COLORS: [u8; 12] = [196, 46, 21, 226, 201, 51, 208, 93, 118, 33, 160, 229];
+const ENERGY_GLYPHS: [char; 10] = [' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'];
+const SPARK_GLYPHS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Plain text picture of the lattice, `#` for up, a space for down and
+/// `.` for vacant sites
+impl fmt::Display for Lattice2d {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let border = format!("+{}+", "-".repeat(self.dims[1]));
+        writeln!(f, "{}", border)?;
+        for row in self.nodes.rows() {
+            write!(f, "|")?;
+            for &spin in row.iter() {
+                let glyph = match spin {
+                    1 => '#',
+                    -1 => ' ',
+                    0 => '.',
+                    _ => '?', // not a spin 1/2 value
+                };
+                write!(f, "{}", glyph)?;
+            }
+            writeln!(f, "|")?;
+        }
+        write!(f, "{}", border)
+    }
+}
+
+/// What the sites are colored by
+pub enum Overlay<'a> {
+    /// The spin values
+    Spins,
+    /// The energy of each site, -s_i * (J * ∑ s_j + h), from low to high
+    Energy,
+    /// A cluster label per site, e.g. from a cluster labelling
+    Clusters(&'a Array2<usize>),
+}
+
+/// Configurable terminal renderer, keeps track of the last frame so that
+/// the next one can be drawn over it
+pub struct TerminalRenderer {
+    color: bool,      // use ANSI 256-color escape codes
+    half_block: bool, // draw two rows of sites per line with '▀'
+    border: bool,     // draw a frame around the lattice
+    in_place: bool,   // move the cursor back up to redraw over the last frame
+    lines_drawn: usize,
+}
+
+impl Default for TerminalRenderer {
+    fn default() -> Self {
+        TerminalRenderer {
+            color: true,
+            half_block: true,
+            border: true,
+            in_place: false,
+            lines_drawn: 0,
+        }
+    }
+}
+
+/// How a single site is drawn
+#[derive(Clone, Copy)]
+struct Cell {
+    color: u8,
+    glyph: char,
+    on: bool,
+}
+
+impl TerminalRenderer {
+    /// Renderer with colors, half-blocks and a border
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Plain renderer without colors or half-blocks, like `Display`
+    pub fn plain() -> Self {
+        Self::new().color(false).half_block(false)
+    }
+
+    /// Use ANSI 256-color escape codes, default true
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Draw two rows of sites per character cell, default true
+    pub fn half_block(mut self, half_block: bool) -> Self {
+        self.half_block = half_block;
+        self
+    }
+
+    /// Draw a frame around the lattice, default true
+    pub fn border(mut self, border: bool) -> Self {
+        self.border = border;
+        self
+    }
+
+    /// Redraw each frame over the previous one, default false
+    pub fn in_place(mut self, in_place: bool) -> Self {
+        self.in_place = in_place;
+        self
+    }
+
+    /// Forget the last frame, the next one is drawn below it
+    pub fn reset(&mut self) {
+        self.lines_drawn = 0;
+    }
+
+    /// Draws one frame of the lattice
+    pub fn render<W: Write>(
+        &mut self,
+        lattice: &Lattice2d,
+        overlay: &Overlay,
+        w: &mut W,
+    ) -> io::Result<()> {
+        let cells = cells(lattice, overlay);
+        let (rows, cols) = cells.dim();
+        if self.in_place && self.lines_drawn > 0 {
+            write!(w, "\x1b[{}A\r", self.lines_drawn)?;
+        }
+
+        // mono half-blocks can only show whether a site is on or off
+        let half_block = self.half_block && (self.color || matches!(overlay, Overlay::Spins));
+        let mut lines = Vec::new();
+        if half_block {
+            for top in (0..rows).step_by(2) {
+                let mut line = String::new();
+                for col in 0..cols {
+                    let upper = cells[[top, col]];
+                    let lower = if top + 1 < rows { Some(cells[[top + 1, col]]) } else { None };
+                    line += &half_block_cell(upper, lower, self.color);
+                }
+                lines.push(line);
+            }
+        } else {
+            for row in cells.rows() {
+                let mut line = String::new();
+                for cell in row.iter() {
+                    if self.color {
+                        line += &format!("\x1b[48;5;{}m ", cell.color);
+                    } else {
+                        line.push(cell.glyph);
+                    }
+                }
+                lines.push(line);
+            }
+        }
+
+        let reset = if self.color { "\x1b[0m" } else { "" };
+        let mut n_lines = 0;
+        if self.border {
+            writeln!(w, "+{}+", "-".repeat(cols))?;
+            n_lines += 1;
+        }
+        for line in lines.iter() {
+            if self.border {
+                writeln!(w, "|{}{}|", line, reset)?;
+            } else {
+                writeln!(w, "{}{}", line, reset)?;
+            }
+            n_lines += 1;
+        }
+        if self.border {
+            writeln!(w, "+{}+", "-".repeat(cols))?;
+            n_lines += 1;
+        }
+        w.flush()?;
+        self.lines_drawn = n_lines;
+        Ok(())
+    }
+}
+
+/// Draws two vertically stacked sites in one character cell
+fn half_block_cell(upper: Cell, lower: Option<Cell>, color: bool) -> String {
+    if color {
+        match lower {
+            Some(lower) => format!("\x1b[38;5;{}m\x1b[48;5;{}m▀", upper.color, lower.color),
+            None => format!("\x1b[0m\x1b[38;5;{}m▀", upper.color),
+        }
+    } else {
+        let lower_on = lower.is_some_and(|cell| cell.on);
+        match (upper.on, lower_on) {
+            (true, true) => "█",
+            (true, false) => "▀",
+            (false, true) => "▄",
+            (false, false) => " ",
+        }
+        .to_owned()
+    }
+}
+
+/// Works out how each site should be drawn
+fn cells(lattice: &Lattice2d, overlay: &Overlay) -> Array2<Cell> {
+    match overlay {
+        Overlay::Spins => lattice.nodes.mapv(|spin| match spin {
+            1 => Cell { color: COLOR_UP, glyph: '#', on: true },
+            -1 => Cell { color: COLOR_DOWN, glyph: ' ', on: false },
+            0 => Cell { color: COLOR_VACANT, glyph: '.', on: false },
+            _ => Cell { color: COLOR_OTHER, glyph: '?', on: false },
+        }),
+        Overlay::Energy => {
+            // per-site energies lie in [-e_max, e_max]
+            let e_max = 4.0 * lattice.j.abs() + lattice.h.abs();
+            lattice.measure_local_energy().mapv(|energy| {
+                let x = if e_max > 0.0 { (energy / e_max + 1.0) / 2.0 } else { 0.5 };
+                Cell {
+                    color: ENERGY_RAMP[ramp_index(x, ENERGY_RAMP.len())],
+                    glyph: ENERGY_GLYPHS[ramp_index(x, ENERGY_GLYPHS.len())],
+                    on: energy > 0.0,
+                }
+            })
+        }
+        Overlay::Clusters(labels) => labels.mapv(|label| Cell {
+            color: CLUSTER_COLORS[label % CLUSTER_COLORS.len()],
+            glyph: std::char::from_digit((label % 36) as u32, 36).unwrap(),
+            on: label % 2 == 1,
+        }),
+    }
+}
+
+/// One line plot of the last `width` values, scaled between their minimum
+/// and maximum. Values that are not finite are drawn as spaces.
+pub fn sparkline(values: &[f64], width: usize) -> String {
+    let values = &values[values.len().saturating_sub(width)..];
+    let finite = values.iter().copied().filter(|x| x.is_finite());
+    let min = finite.clone().fold(f64::INFINITY, f64::min);
+    let max = finite.fold(f64::NEG_INFINITY, f64::max);
+    values
+        .iter()
+        .map(|&x| {
+            if !x.is_finite() {
+                ' '
+            } else if max > min {
+                SPARK_GLYPHS[ramp_index((x - min) / (max - min), SPARK_GLYPHS.len())]
+            } else {
+                SPARK_GLYPHS[SPARK_GLYPHS.len() / 2] // flat line
+            }
+        })
+        .collect()
+}
+
+/// Index into a ramp of length n for x in [0, 1]
+fn ramp_index(x: f64, n: usize) -> usize {
+    ((x.clamp(0.0, 1.0) * (n - 1) as f64).round() as usize).min(n - 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lattice2d::*;
+
+    fn small_lattice() -> Lattice2d {
+        let arr = array![[1, -1, 1], [-1, -1, 1], [1, 1, -1]];
+        Lattice2d::builder()
+            .dims([3, 3])
+            .init_type(InitType::FromArray(arr))
+            .build()
+            .unwrap()
+    }
+
+    fn render_to_string(renderer: &mut TerminalRenderer, lattice: &Lattice2d, overlay: &Overlay) -> String {
+        let mut out = Vec::new();
+        renderer.render(lattice, overlay, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_display() {
+        let lattice = small_lattice();
+        assert_eq!(format!("{}", lattice), "+---+\n|# #|\n|  #|\n|## |\n+---+");
+    }
+
+    #[test]
+    fn test_render_plain_matches_display() {
+        let lattice = small_lattice();
+        let out = render_to_string(&mut TerminalRenderer::plain(), &lattice, &Overlay::Spins);
+        assert_eq!(out, format!("{}\n", lattice));
+    }
+
+    #[test]
+    fn test_render_mono_half_block() {
+        let lattice = small_lattice();
+        let mut renderer = TerminalRenderer::new().color(false).border(false);
+        let out = render_to_string(&mut renderer, &lattice, &Overlay::Spins);
+        assert_eq!(out, "▀ █\n▀▀ \n");
+    }
+
+    #[test]
+    fn test_render_color_and_in_place() {
+        let lattice = small_lattice();
+        let mut renderer = TerminalRenderer::new().in_place(true);
+        let first = render_to_string(&mut renderer, &lattice, &Overlay::Spins);
+        assert!(first.contains(&format!("\x1b[38;5;{}m", COLOR_UP)));
+        assert!(!first.starts_with("\x1b[4A"));
+        assert_eq!(first.lines().count(), 4); // border, two half-block lines, border
+        let second = render_to_string(&mut renderer, &lattice, &Overlay::Energy);
+        assert!(second.starts_with("\x1b[4A\r"));
+    }
+
+    #[test]
+    fn test_render_overlays() {
+        let lattice = small_lattice();
+        let labels = array![[0, 1, 2], [1, 1, 2], [3, 3, 4]];
+        let mut renderer = TerminalRenderer::plain().border(false);
+        let out = render_to_string(&mut renderer, &lattice, &Overlay::Clusters(&labels));
+        assert_eq!(out, "012\n112\n334\n");
+
+        let all_up = Lattice2d::builder().dims([2, 2]).init_type(InitType::AllUp).build().unwrap();
+        let out = render_to_string(&mut renderer, &all_up, &Overlay::Energy);
+        assert_eq!(out, "  \n  \n"); // every site at the lowest energy
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[0.0, 1.0, 2.0, 3.0, 7.0], 5), "▁▂▃▄█");
+        assert_eq!(sparkline(&[9.0, 0.0, 1.0], 2), "▁█"); // only the last values
+        assert_eq!(sparkline(&[2.0, 2.0], 10), "▅▅");
+        assert_eq!(sparkline(&[0.0, f64::NAN, 1.0], 3), "▁ █");
+        assert_eq!(sparkline(&[], 3), "");
+    }
+}
@@ -0,0 +1,379 @@
+//! Labelled Monte Carlo results. A `SampleSet` keeps the samples of one
+//! observable together with its name and the parameters they were taken
+//! with, and summarizes them, pooled or run by run. A `BlockSampleSet`
+//! does the same for the block magnetizations, block by block.
+//!
+//! ```
+//! use ising_lib::lattice2d::Lattice2d;
+//! use ising_lib::monte_carlo_measurement::{MonteCarlo, MonteCarloParams};
+//!
+//! let params = MonteCarloParams {
+//!     n_runs: 4,
+//!     flips_to_skip: 1_000,
+//!     samples_per_run: 5,
+//!     flips_to_skip_between_samples: 50,
+//! };
+//! let mut lattice = Lattice2d::builder().dims([8, 8]).beta(0.3).build().unwrap();
+//! let energy = lattice.sample_energy(&params);
+//! assert_eq!(energy.name, "energy");
+//! assert_eq!(energy.n_samples(), 20);
+//! // samples within a run are correlated, the error comes from the runs
+//! let error = energy.sem();
+//! assert_eq!(error, energy.between_runs().sem);
+//! assert_eq!(energy.per_run().len(), 4);
+//! ```
+
+use crate::lattice2d::Lattice2d;
+use crate::monte_carlo_measurement::MonteCarloParams;
+use crate::observable::Observable;
+use ndarray::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The samples of one observable and what they were sampled with
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SampleSet {
+    pub name: String,
+    pub dims: [usize; 2],
+    pub beta: f64,
+    pub h: f64,
+    pub j: f64,
+    pub params: MonteCarloParams,
+    pub samples: Vec<Vec<f64>>, // one vec per run, in the order of sampling
+}
+
+/// Summary statistics of a list of values, NaN where undefined
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Stats {
+    pub n: usize,
+    pub mean: f64,
+    pub variance: f64, // the sample variance, with n - 1
+    pub std: f64,
+    pub sem: f64, // std / √n, the standard error if the values are independent
+}
+
+impl Stats {
+    pub fn of(values: &[f64]) -> Self {
+        let n = values.len();
+        let mean = values.iter().sum::<f64>() / n as f64;
+        let variance = if n > 1 {
+            values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64
+        } else {
+            f64::NAN
+        };
+        let std = variance.sqrt();
+        Stats {
+            n,
+            mean,
+            variance,
+            std,
+            sem: std / (n as f64).sqrt(),
+        }
+    }
+
+    /// Statistics of the samples of all runs together, see
+    /// `SampleSet::pooled`
+    pub fn pooled(samples: &[Vec<f64>]) -> Self {
+        let all: Vec<f64> = samples.iter().flatten().copied().collect();
+        Stats::of(&all)
+    }
+
+    /// Statistics of the means of the runs, see `SampleSet::between_runs`
+    pub fn between_runs(samples: &[Vec<f64>]) -> Self {
+        Stats::of(&run_means(samples))
+    }
+}
+
+/// The mean of each run, skipping empty runs
+fn run_means(samples: &[Vec<f64>]) -> Vec<f64> {
+    samples
+        .iter()
+        .filter(|run| !run.is_empty())
+        .map(|run| run.iter().sum::<f64>() / run.len() as f64)
+        .collect()
+}
+
+impl SampleSet {
+    /// The samples of `name`, taken on `lattice` with `params`
+    pub fn new(name: &str, lattice: &Lattice2d, params: &MonteCarloParams, samples: Vec<Vec<f64>>) -> Self {
+        SampleSet {
+            name: name.to_owned(),
+            dims: lattice.dims,
+            beta: lattice.beta,
+            h: lattice.h,
+            j: lattice.j,
+            params: *params,
+            samples,
+        }
+    }
+
+    pub fn n_runs(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn n_samples(&self) -> usize {
+        self.samples.iter().map(|run| run.len()).sum()
+    }
+
+    /// Statistics of all samples together. The sem treats them as
+    /// independent, which underestimates the error of correlated samples,
+    /// see `between_runs`.
+    pub fn pooled(&self) -> Stats {
+        Stats::pooled(&self.samples)
+    }
+
+    /// Statistics of the samples of each run
+    pub fn per_run(&self) -> Vec<Stats> {
+        self.samples.iter().map(|run| Stats::of(run)).collect()
+    }
+
+    /// The mean of each run, skipping empty runs
+    pub fn run_means(&self) -> Vec<f64> {
+        run_means(&self.samples)
+    }
+
+    /// Statistics of the run means. The runs are independent, so this sem
+    /// is the error of the mean even when the samples of a run are not.
+    pub fn between_runs(&self) -> Stats {
+        Stats::between_runs(&self.samples)
+    }
+
+    /// The mean of all samples
+    pub fn mean(&self) -> f64 {
+        self.pooled().mean
+    }
+
+    /// The sample variance of all samples
+    pub fn variance(&self) -> f64 {
+        self.pooled().variance
+    }
+
+    /// The standard deviation of all samples
+    pub fn std(&self) -> f64 {
+        self.pooled().std
+    }
+
+    /// The standard error of the mean, from the spread of the run means
+    pub fn sem(&self) -> f64 {
+        self.between_runs().sem
+    }
+
+    /// Mean and sample standard deviation of all samples, and the standard
+    /// error of the mean from the spread of the run means (samples within a
+    /// run are correlated, the runs are independent). NaN where undefined.
+    pub fn mean_std_sem(&self) -> (f64, f64, f64) {
+        let pooled = self.pooled();
+        (pooled.mean, pooled.std, self.sem())
+    }
+}
+
+/// The block magnetizations of each sample, see
+/// `Measurement::measure_block_magnetizations`, and what they were
+/// sampled with
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BlockSampleSet {
+    pub block: usize, // the side of the blocks, in sites
+    pub dims: [usize; 2],
+    pub beta: f64,
+    pub h: f64,
+    pub j: f64,
+    pub params: MonteCarloParams,
+    pub samples: Vec<Vec<Array2<f64>>>, // one vec per run, in the order of sampling
+}
+
+impl BlockSampleSet {
+    /// The block magnetizations of `block` x `block` squares, taken on
+    /// `lattice` with `params`
+    pub fn new(block: usize, lattice: &Lattice2d, params: &MonteCarloParams, samples: Vec<Vec<Array2<f64>>>) -> Self {
+        BlockSampleSet {
+            block,
+            dims: lattice.dims,
+            beta: lattice.beta,
+            h: lattice.h,
+            j: lattice.j,
+            params: *params,
+            samples,
+        }
+    }
+
+    /// The number of blocks along each dim
+    pub fn n_blocks(&self) -> [usize; 2] {
+        let block = self.block.max(1);
+        self.dims.map(|d| d.div_ceil(block))
+    }
+
+    /// The samples of the block at [bi, bj], named `block_bi_bj`
+    pub fn block_samples(&self, bi: usize, bj: usize) -> SampleSet {
+        SampleSet {
+            name: format!("block_{}_{}", bi, bj),
+            dims: self.dims,
+            beta: self.beta,
+            h: self.h,
+            j: self.j,
+            params: self.params,
+            samples: self
+                .samples
+                .iter()
+                .map(|run| run.iter().map(|blocks| blocks[[bi, bj]]).collect())
+                .collect(),
+        }
+    }
+
+    /// Statistics of all samples of each block, see `SampleSet::pooled`
+    pub fn pooled(&self) -> Array2<Stats> {
+        let [rows, cols] = self.n_blocks();
+        Array2::from_shape_fn((rows, cols), |(bi, bj)| self.block_samples(bi, bj).pooled())
+    }
+
+    /// Statistics of the run means of each block, see
+    /// `SampleSet::between_runs`
+    pub fn between_runs(&self) -> Array2<Stats> {
+        let [rows, cols] = self.n_blocks();
+        Array2::from_shape_fn((rows, cols), |(bi, bj)| self.block_samples(bi, bj).between_runs())
+    }
+
+    /// The mean of all samples of each block
+    pub fn mean(&self) -> Array2<f64> {
+        self.pooled().mapv(|stats| stats.mean)
+    }
+
+    /// The standard error of the mean of each block, from the spread of
+    /// the run means
+    pub fn sem(&self) -> Array2<f64> {
+        self.between_runs().mapv(|stats| stats.sem)
+    }
+}
+
+/// Regroups samples of all observables, indexed [run][sample][observable],
+/// into a sample set per observable
+pub(crate) fn sample_sets(
+    observables: &[&dyn Observable],
+    lattice: &Lattice2d,
+    params: &MonteCarloParams,
+    samples: Vec<Vec<Vec<f64>>>,
+) -> Vec<SampleSet> {
+    let names: Vec<&str> = observables.iter().map(|observable| observable.name()).collect();
+    named_sets(&names, lattice, params, &samples)
+}
+
+/// Splits the even and odd sublattice magnetizations, indexed
+/// [run][sample], into the sample sets `sublattice_even` and
+/// `sublattice_odd`
+pub(crate) fn sublattice_sets(lattice: &Lattice2d, params: &MonteCarloParams, samples: Vec<Vec<[f64; 2]>>) -> [SampleSet; 2] {
+    named_sets(&["sublattice_even", "sublattice_odd"], lattice, params, &samples)
+        .try_into()
+        .unwrap()
+}
+
+fn named_sets<V: AsRef<[f64]>>(
+    names: &[&str],
+    lattice: &Lattice2d,
+    params: &MonteCarloParams,
+    samples: &[Vec<V>],
+) -> Vec<SampleSet> {
+    names
+        .iter()
+        .enumerate()
+        .map(|(k, name)| {
+            let series = samples
+                .iter()
+                .map(|run| run.iter().map(|values| values.as_ref()[k]).collect())
+                .collect();
+            SampleSet::new(name, lattice, params, series)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::observable::Quantity;
+
+    fn params() -> MonteCarloParams {
+        MonteCarloParams {
+            n_runs: 2,
+            flips_to_skip: 0,
+            samples_per_run: 2,
+            flips_to_skip_between_samples: 0,
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = Stats::of(&[1.0, 2.0, 3.0, 6.0]);
+        assert_eq!(stats.n, 4);
+        assert_eq!(stats.mean, 3.0);
+        assert_eq!(stats.variance, 14.0 / 3.0);
+        assert_eq!(stats.sem, stats.std / 2.0);
+        assert!(Stats::of(&[1.0]).variance.is_nan());
+        assert!(Stats::of(&[]).mean.is_nan());
+
+        let samples = [vec![1.0, 3.0], vec![5.0, 7.0], vec![]];
+        assert_eq!(Stats::pooled(&samples), Stats::of(&[1.0, 3.0, 5.0, 7.0]));
+        assert_eq!(Stats::between_runs(&samples), Stats::of(&[2.0, 6.0]));
+        assert!(Stats::pooled(&[vec![1.0]]).std.is_nan());
+    }
+
+    #[test]
+    fn test_sample_set() {
+        let lattice = Lattice2d::builder().dims([4, 6]).beta(0.5).h(0.1).build().unwrap();
+        let set = SampleSet::new("energy", &lattice, &params(), vec![vec![1.0, 3.0], vec![5.0, 7.0]]);
+        assert_eq!(set.dims, [4, 6]);
+        assert_eq!((set.beta, set.h, set.j), (0.5, 0.1, 1.0));
+        assert_eq!(set.n_runs(), 2);
+        assert_eq!(set.n_samples(), 4);
+        assert_eq!(set.mean(), 4.0);
+        assert_eq!(set.variance(), 20.0 / 3.0);
+        assert_eq!(set.run_means(), vec![2.0, 6.0]);
+        assert_eq!(set.per_run()[1].mean, 6.0);
+        assert_eq!(set.per_run()[0].variance, 2.0);
+        // the run means 2 and 6 have std 2√2, over √2 runs
+        assert!((set.sem() - 2.0).abs() < 1e-12);
+        assert_eq!(set.mean_std_sem(), (set.mean(), set.std(), set.sem()));
+    }
+
+    #[test]
+    fn test_sample_sets() {
+        let lattice = Lattice2d::builder().dims([4, 4]).build().unwrap();
+        let observables: [&dyn Observable; 2] = [&Quantity::Energy, &Quantity::Magnetization];
+        let samples = vec![vec![vec![-1.0, 0.5], vec![-2.0, 0.25]], vec![vec![-3.0, 0.0]]];
+        let sets = sample_sets(&observables, &lattice, &params(), samples);
+        assert_eq!(sets[0].name, "energy");
+        assert_eq!(sets[0].samples, vec![vec![-1.0, -2.0], vec![-3.0]]);
+        assert_eq!(sets[1].samples, vec![vec![0.5, 0.25], vec![0.0]]);
+        assert_eq!(sets[0].mean(), -2.0);
+        assert_eq!(sets[1].params, params());
+    }
+
+    #[test]
+    fn test_sublattice_sets() {
+        let lattice = Lattice2d::builder().dims([4, 4]).build().unwrap();
+        let samples = vec![vec![[1.0, -1.0], [0.5, -0.5]], vec![[0.0, 0.25]]];
+        let [even, odd] = sublattice_sets(&lattice, &params(), samples);
+        assert_eq!(even.name, "sublattice_even");
+        assert_eq!(even.samples, vec![vec![1.0, 0.5], vec![0.0]]);
+        assert_eq!(odd.name, "sublattice_odd");
+        assert_eq!(odd.samples, vec![vec![-1.0, -0.5], vec![0.25]]);
+    }
+
+    #[test]
+    fn test_block_sample_set() {
+        let lattice = Lattice2d::builder().dims([4, 6]).build().unwrap();
+        let samples = vec![
+            vec![array![[1.0, 0.0], [0.5, -1.0]], array![[3.0, 0.0], [0.5, -1.0]]],
+            vec![array![[5.0, 0.0], [0.5, -1.0]], array![[7.0, 0.0], [0.5, -1.0]]],
+        ];
+        let blocks = BlockSampleSet::new(3, &lattice, &params(), samples);
+        assert_eq!(blocks.n_blocks(), [2, 2]);
+        let corner = blocks.block_samples(0, 0);
+        assert_eq!(corner.name, "block_0_0");
+        assert_eq!(corner.samples, vec![vec![1.0, 3.0], vec![5.0, 7.0]]);
+        assert_eq!(blocks.mean(), array![[4.0, 0.0], [0.5, -1.0]]);
+        assert_eq!(blocks.pooled()[[0, 0]], corner.pooled());
+        assert!((blocks.sem()[[0, 0]] - 2.0).abs() < 1e-12);
+        assert_eq!(blocks.sem()[[1, 1]], 0.0);
+    }
+}
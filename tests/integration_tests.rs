@@ -1,5 +1,6 @@
 use ising_lib::lattice2d::{InitType, Lattice2d, SpinType, UpdateRule}; 
 use ising_lib::monte_carlo_measurement::{MonteCarlo, MonteCarloParams};
+use ising_lib::sample_set::SampleSet;
 
 #[test]
 fn integration_test_test() {
@@ -18,5 +19,7 @@ fn integration_test_test() {
         0.1f64, // h, static external field term
         2.4f64, // 1/TkB
     );
-    let _nn_samples:Vec<Vec<f64>> = lattice.sample_neighbor_correlations(&params);
+    let nn_samples: SampleSet = lattice.sample_neighbor_correlations(&params);
+    assert_eq!(nn_samples.n_samples(), 15);
+    assert_eq!(nn_samples.dims, [8, 9]);
 }